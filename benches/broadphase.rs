@@ -0,0 +1,128 @@
+//! Compares sweeping balls against every brick with sweeping them against only the bricks that
+//! `World::brick_grid` says are nearby, and times a full `World::step` at the same ball counts.
+//!
+//! ```text
+//! cargo bench --no-default-features --bench broadphase
+//! ```
+
+use breakout::math::Aabb;
+use breakout::timestep::DEFAULT_TICK_RATE;
+use breakout::world::{Ball, FrameInput, World};
+
+use rand::prelude::*;
+use ultraviolet::Vec2;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const BALL_COUNTS: [usize; 3] = [1, 100, 10_000];
+
+/// Run `f` until about this much time has passed, then report the average
+const TARGET_TIME: Duration = Duration::from_millis(500);
+
+fn time_per_iter(mut f: impl FnMut()) -> Duration {
+    // Warm up
+    f();
+
+    let mut iters = 0;
+    let start = Instant::now();
+    while start.elapsed() < TARGET_TIME {
+        f();
+        iters += 1;
+    }
+
+    start.elapsed() / iters
+}
+
+/// The default board, with `count` balls scattered over the bottom half of it.
+///
+/// That puts plenty of them in amongst the bricks, so bricks break (and the grid gets rebuilt) every step.
+fn world_with_balls(count: usize) -> World {
+    let aspect = breakout::WINDOW_HEIGHT as f32 / breakout::WINDOW_WIDTH as f32;
+    let mut world = World::new(aspect, 0xb0a7);
+    let mut rng = StdRng::seed_from_u64(0xb0a7);
+
+    world.balls = (0..count)
+        .map(|id| {
+            let pos = Vec2::new(
+                rng.random_range(1.0..world.bounds.x - 1.),
+                rng.random_range(0.1 * world.bounds.y..0.5 * world.bounds.y),
+            );
+            let angle: f32 = rng.random_range(0.0..std::f32::consts::TAU);
+
+            Ball {
+                id: id as u32,
+                pos,
+                vel: world.rules.ball_speed * Vec2::new(angle.cos(), angle.sin()),
+                radius: world.rules.ball_radius,
+                player: 0,
+                stuck: None,
+            }
+        })
+        .collect();
+    world.next_ball_id = count as u32;
+
+    world
+}
+
+fn main() {
+    let dt = 1. / DEFAULT_TICK_RATE;
+
+    println!(
+        "{:>8} | {:>14} | {:>14} | {:>8} | {:>14}",
+        "balls", "every brick", "brick_grid", "speedup", "World::step"
+    );
+
+    for count in BALL_COUNTS {
+        let world = world_with_balls(count);
+        let boxes: Vec<Aabb> = world
+            .bricks
+            .iter()
+            .map(|brick| &brick.quad)
+            .chain(&world.unbreakable_bricks)
+            .map(|quad| Aabb::new_from_quad(quad.pos, quad.dims))
+            .collect();
+
+        let brute_force = time_per_iter(|| {
+            let mut hits = 0;
+            for ball in &world.balls {
+                let motion = dt * ball.vel;
+                for aabb in &boxes {
+                    hits += aabb
+                        .intersects_with_circle_sweep(ball.pos, ball.radius, motion)
+                        .is_some() as usize;
+                }
+            }
+            black_box(hits);
+        });
+
+        let mut nearby = vec![];
+        let grid = time_per_iter(|| {
+            let mut hits = 0;
+            for ball in &world.balls {
+                let motion = dt * ball.vel;
+                let swept =
+                    Aabb::new_from_quad(ball.pos, motion).padded(Vec2::broadcast(ball.radius));
+                world.brick_grid.query(&swept, &mut nearby);
+
+                for &id in &nearby {
+                    hits += boxes[id as usize]
+                        .intersects_with_circle_sweep(ball.pos, ball.radius, motion)
+                        .is_some() as usize;
+                }
+            }
+            black_box(hits);
+        });
+
+        let mut next = World::default();
+        let step = time_per_iter(|| {
+            world.step_into(&FrameInput::default(), dt, &mut next);
+            black_box(&next);
+        });
+
+        println!(
+            "{count:>8} | {brute_force:>14.2?} | {grid:>14.2?} | {:>7.1}x | {step:>14.2?}",
+            brute_force.as_secs_f64() / grid.as_secs_f64(),
+        );
+    }
+}
@@ -20,6 +20,11 @@ fn rerun_if_changed(path: impl AsRef<std::path::Path>) {
 }
 
 fn main() {
+    // Shaders and Swift code are only used by the game itself, so skip them for headless-only builds
+    if env::var_os("CARGO_FEATURE_GFX").is_none() {
+        return;
+    }
+
     build_shaders();
     build_swift();
 }
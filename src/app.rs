@@ -0,0 +1,827 @@
+use fermium::prelude::*;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use ultraviolet::Vec2;
+
+use crate::audio::*;
+use crate::color;
+use crate::controller::{Ai, PaddleController, Skill};
+use crate::editor::Editor;
+use crate::game::*;
+use crate::generate::{generate, Params};
+use crate::gfx::*;
+use crate::history::History;
+use crate::level::{Cell, Level};
+use crate::replay::Replay;
+use crate::rng::random_seed;
+use crate::snapshot;
+use crate::timestep::*;
+use crate::world::*;
+use crate::PADDLE_COLORS;
+
+/// Command line options for the game
+struct Options {
+    /// Simulation ticks per second
+    tick_rate: f32,
+
+    /// Most ticks to run in a single frame when we fall behind
+    max_catchup_steps: u32,
+
+    /// Seed for the world's rng, random unless asked for
+    seed: u64,
+
+    /// Level file to play instead of the default level
+    level: Option<String>,
+
+    /// Seed for a generated board to play instead of the default level. This wins over `level`.
+    generate: Option<u64>,
+
+    /// Where to save a replay of the game when we quit
+    record: Option<String>,
+
+    /// Replay file to watch. It brings its own seed, level and tick rate.
+    replay: Option<String>,
+
+    /// Who's playing
+    mode: Mode,
+
+    /// Let the AI play every paddle until someone presses Return to start a game. Turned on by `--attract`.
+    attract: bool,
+
+    /// Let the AI steer the first player's paddle whenever they aren't. Turned on by `--assist`.
+    assist: bool,
+
+    /// How well the AI plays
+    skill: Skill,
+}
+
+impl Options {
+    fn from_args() -> Self {
+        let mut options = Options {
+            tick_rate: DEFAULT_TICK_RATE,
+            max_catchup_steps: DEFAULT_MAX_CATCHUP_STEPS,
+            seed: random_seed(),
+            level: None,
+            generate: None,
+            record: None,
+            replay: None,
+            mode: Mode::Single,
+            attract: false,
+            assist: false,
+            skill: Skill::default(),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next().unwrap_or_else(|| {
+                    eprintln!("Missing value for {arg}");
+                    std::process::exit(1);
+                })
+            };
+
+            match arg.as_str() {
+                "--tick-rate" => {
+                    options.tick_rate =
+                        value().parse().expect("--tick-rate expects a number of Hz");
+                    if !options.tick_rate.is_finite() || options.tick_rate <= 0. {
+                        eprintln!(
+                            "Bad value for --tick-rate: {} isn't a positive number of Hz",
+                            options.tick_rate
+                        );
+                        std::process::exit(1);
+                    }
+                }
+                "--max-catchup" => {
+                    options.max_catchup_steps = value()
+                        .parse()
+                        .expect("--max-catchup expects a number of ticks");
+                    if options.max_catchup_steps == 0 {
+                        eprintln!("Bad value for --max-catchup: it needs to run at least 1 tick");
+                        std::process::exit(1);
+                    }
+                }
+                "--seed" => {
+                    options.seed = value().parse().expect("--seed expects a whole number");
+                }
+                "--level" => {
+                    options.level = Some(value());
+                }
+                "--generate" => {
+                    options.generate =
+                        Some(value().parse().expect("--generate expects a whole number"));
+                }
+                "--record" => {
+                    options.record = Some(value());
+                }
+                "--replay" => {
+                    options.replay = Some(value());
+                }
+                "--mode" => {
+                    options.mode =
+                        Mode::from_name(&value()).expect("--mode expects single, co-op or versus");
+                }
+                "--attract" => {
+                    options.attract = true;
+                }
+                "--assist" => {
+                    options.assist = true;
+                }
+                "--skill" => {
+                    options.skill = Skill::from_name(&value())
+                        .expect("--skill expects easy, normal or perfect");
+                }
+                _ => {
+                    println!("Ignoring unknown argument \"{arg}\"");
+                }
+            }
+        }
+
+        options
+    }
+}
+
+/// Where the editor saves when we didn't load a level file
+const DEFAULT_SAVE_PATH: &str = "levels/custom.txt";
+
+/// Where F5 saves the game and F9 loads it from
+const QUICKSAVE_PATH: &str = "quicksave.json";
+
+/// How far back holding R can rewind, in seconds
+const REWIND_SECONDS: f32 = 10.;
+
+/// We only keep every few ticks for rewinding, and rewinding goes this many times faster than real time
+const REWIND_STRIDE: u64 = 4;
+
+/// Everyone's points, like "120" with one player or "P1 120, P2 80" with more
+fn describe_scores(game: &Game) -> String {
+    match &game.players[..] {
+        [player] => player.score.points.to_string(),
+        players => {
+            let scores: Vec<_> = players
+                .iter()
+                .enumerate()
+                .map(|(i, player)| format!("P{} {}", i + 1, player.score.points))
+                .collect();
+            scores.join(", ")
+        }
+    }
+}
+
+/// Where a point in the window lands in the world. The view stretches the world over the whole window, and
+/// the world's y goes up instead of down.
+fn window_to_world(window: &Window, bounds: Vec2, x: i32, y: i32) -> Vec2 {
+    let (width, height) = window.size();
+
+    Vec2::new(
+        bounds.x * x as f32 / width as f32,
+        bounds.y * (1. - y as f32 / height as f32),
+    )
+}
+
+fn describe_cell(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => "nothing".into(),
+        Cell::Brick(brick) => format!("a {} hp brick worth {}", brick.hp, brick.points),
+        Cell::Wall(_) => "a wall".into(),
+    }
+}
+
+fn poll_event() -> Option<SDL_Event> {
+    let mut e = SDL_Event::default();
+    if unsafe { SDL_PollEvent(&mut e) == 1 } {
+        Some(e)
+    } else {
+        None
+    }
+}
+
+// This 'static is kind of a lie - values here can and will update while this
+// reference exists. We only read it AFTER calling SDL_PollEvent, so this is safe.
+struct KeyboardState(&'static [u8]);
+
+impl core::ops::Index<SDL_Scancode> for KeyboardState {
+    type Output = u8;
+
+    fn index(&self, index: SDL_Scancode) -> &Self::Output {
+        &self.0[index.0 as usize]
+    }
+}
+
+fn get_keyboard_state() -> KeyboardState {
+    unsafe {
+        let mut num_keys: i32 = 0;
+        let ptr = SDL_GetKeyboardState(&mut num_keys);
+
+        KeyboardState(std::slice::from_raw_parts(ptr, num_keys as usize))
+    }
+}
+
+pub fn app_main() {
+    const SAMPLE_FREQ: u32 = 44_100;
+
+    let mut options = Options::from_args();
+
+    // A replay only plays out the same way again with everything it was recorded with
+    let replay = options.replay.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        })
+    });
+    if let Some(replay) = &replay {
+        options.seed = replay.seed;
+        options.tick_rate = replay.tick_rate;
+        options.mode = Mode::Single;
+    }
+    if options.record.is_some() && options.mode != Mode::Single {
+        eprintln!("Replays only have room for one player, so --record needs --mode single");
+        std::process::exit(1);
+    }
+
+    let waveform1 = SquareWaveform::new(SAMPLE_FREQ, 220);
+    let waveform2 = SawtoothWaveform::new(SAMPLE_FREQ, 220);
+    let _waveform = CombinedWaveforms::new(SAMPLE_FREQ, 1, waveform1, waveform2);
+    let audio_player = AudioPlayer::new(SAMPLE_FREQ, 1, waveform2);
+
+    let window_width: i32 = crate::WINDOW_WIDTH;
+    let window_height: i32 = crate::WINDOW_HEIGHT;
+
+    // Initialize graphics & UI
+    let window = Window::new(window_width, window_height);
+    let mut gpu = GpuDevice::new(&window);
+
+    println!("Seed: {}", options.seed);
+    let layout = match (&replay, &options.level, options.generate) {
+        (Some(replay), _, _) => replay.level.clone(),
+        (None, _, Some(level_seed)) => generate(level_seed, &Params::default()),
+        (None, Some(path), None) => Level::load(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }),
+        (None, None, None) => Level::default_level(),
+    };
+    let aspect = match &replay {
+        Some(replay) => replay.aspect,
+        None => window_height as f32 / window_width as f32,
+    };
+
+    // We keep the last two ticks around to blend between them when rendering, and one more to step into
+    let mut game = Game::from_level_with(Arc::new(layout), aspect, options.seed, options.mode);
+    let mut prev = game.clone();
+    let mut next = Game::default();
+    gpu.set_view(game.world.bounds.x, game.world.bounds.y);
+
+    let mut paused = false;
+    let mut capture: Option<GpuCapture> = None;
+
+    // Set while we're editing the board instead of playing on it
+    let mut editor: Option<Editor> = None;
+    let save_path = options.level.clone().unwrap_or(DEFAULT_SAVE_PATH.into());
+
+    // What the board being edited looks like, rebuilt whenever it changes
+    let mut preview = World::default();
+    let mut preview_stale = false;
+
+    // The mouse, in world coordinates
+    let mut cursor = Vec2::zero();
+
+    // Every tick's input goes in here when we're recording, and comes out of here when we're watching a replay
+    let new_recording =
+        |layout: &Level| Replay::new(layout.clone(), aspect, options.seed, options.tick_rate);
+    let mut recording = options.record.as_ref().map(|_| new_recording(&game.layout));
+    let mut playback = replay.as_ref().map(|replay| replay.inputs());
+
+    // The last few seconds of the game, for rewinding, and how many ticks the game has had so far
+    let mut history = History::new(REWIND_SECONDS, options.tick_rate, REWIND_STRIDE);
+    let mut ticks_played: u64 = 0;
+    let mut rewinding = false;
+
+    let mut timestep = FixedTimestep::new(options.tick_rate, options.max_catchup_steps);
+    println!(
+        "Simulating at {} ticks per second, catching up at most {} ticks per frame",
+        options.tick_rate, options.max_catchup_steps
+    );
+
+    // Presses that happen between ticks are held here until a tick uses them, one input for each player.
+    // The first player has the arrow keys, and the second has A, D and W.
+    let mut inputs = vec![FrameInput::default(); game.players.len()];
+    let mut tick_inputs = vec![];
+
+    // The AI plays everyone until someone starts a game in attract mode, and helps the first player steer with
+    // --assist. Each player gets their own, so they all see the game from their own paddle.
+    let mut attract = options.attract;
+    let new_ai = |player: usize| {
+        Ai::new(
+            options.skill,
+            options.tick_rate,
+            options.seed.wrapping_add(player as u64),
+        )
+    };
+    let mut ais: Vec<Ai> = vec![];
+    if attract {
+        println!("Attract mode. Press Return to play");
+    }
+
+    audio_player.play();
+    window.show();
+
+    'main_loop: loop {
+        // == Handle events ====================================================
+
+        // Pump key presses
+        // Note: Not all key events are handled here.
+        while let Some(e) = poll_event() {
+            // Access to unions is unsafe, so this match block is going to get spicy
+            let type_ = unsafe { e.type_ };
+            match type_ {
+                // Immediately quit everything - unhandled events are forever ignored
+                SDL_QUIT => {
+                    break 'main_loop;
+                }
+
+                SDL_WINDOWEVENT => {
+                    let window_event = unsafe { e.window };
+                    if window_event.event == SDL_WINDOWEVENT_RESIZED {
+                        // gpu will query the drawable size internally, so we don't pass it anything
+                        gpu.on_view_resize();
+                    }
+                }
+
+                SDL_KEYDOWN if editor.is_some() => {
+                    let key = unsafe { e.key };
+                    let Some(ed) = &mut editor else {
+                        unreachable!()
+                    };
+                    let ctrl = key.keysym.mod_ as i32 & KMOD_CTRL.0 != 0;
+                    let shift = key.keysym.mod_ as i32 & KMOD_SHIFT.0 != 0;
+
+                    match key.keysym.sym {
+                        keycode::SDLK_q => {
+                            break 'main_loop;
+                        }
+
+                        keycode::SDLK_e | keycode::SDLK_ESCAPE if key.repeat == 0 => {
+                            // Back to the game we left, as it was
+                            editor = None;
+                            gpu.set_view(game.world.bounds.x, game.world.bounds.y);
+                            println!("Done editing");
+                        }
+
+                        keycode::SDLK_p if key.repeat == 0 => {
+                            // Start a new game on the board we're editing
+                            let layout = Arc::new(ed.level.clone());
+                            game =
+                                Game::from_level_with(layout, aspect, options.seed, options.mode);
+                            prev = game.clone();
+                            gpu.set_view(game.world.bounds.x, game.world.bounds.y);
+                            editor = None;
+                            paused = false;
+                            playback = None;
+                            history.clear();
+                            ticks_played = 0;
+                            println!("Playtesting");
+
+                            // The old recording doesn't lead up to this, so start a new one
+                            if let Some(recording) = &mut recording {
+                                *recording = new_recording(&game.layout);
+                            }
+                        }
+
+                        keycode::SDLK_z if ctrl && shift => {
+                            preview_stale |= ed.redo();
+                        }
+
+                        keycode::SDLK_z if ctrl => {
+                            preview_stale |= ed.undo();
+                        }
+
+                        keycode::SDLK_y if ctrl => {
+                            preview_stale |= ed.redo();
+                        }
+
+                        keycode::SDLK_s if ctrl && key.repeat == 0 => match ed.save(&save_path) {
+                            Ok(()) => println!("Saved to {save_path}"),
+                            Err(e) => eprintln!("{e}"),
+                        },
+
+                        sym if (keycode::SDLK_1.0..=keycode::SDLK_9.0).contains(&sym.0) => {
+                            // Pick what to paint
+                            let selected = (sym.0 - keycode::SDLK_1.0) as usize;
+                            if let Some(cell) = ed.palette.get(selected) {
+                                ed.selected = selected;
+                                println!("Painting {}", describe_cell(cell));
+                            }
+                        }
+
+                        _ => {}
+                    }
+                }
+
+                SDL_KEYDOWN => {
+                    let key = unsafe { e.key };
+
+                    match key.keysym.sym {
+                        keycode::SDLK_q => {
+                            // Quit the app when "Q" is pressed
+                            break 'main_loop;
+                        }
+
+                        keycode::SDLK_SPACE if key.repeat == 0 => {
+                            // Toggle the simulation update when SPACE is pressed
+                            paused = !paused;
+                        }
+
+                        keycode::SDLK_UP | keycode::SDLK_RETURN if key.repeat == 0 && attract => {
+                            // Take over from the AI with a new game
+                            let layout = game.layout.clone();
+                            game =
+                                Game::from_level_with(layout, aspect, options.seed, options.mode);
+                            prev = game.clone();
+                            attract = false;
+                            ais.clear();
+                            playback = None;
+                            history.clear();
+                            ticks_played = 0;
+                            println!("Your turn!");
+
+                            if let Some(recording) = &mut recording {
+                                *recording = new_recording(&game.layout);
+                            }
+                        }
+
+                        keycode::SDLK_UP | keycode::SDLK_RETURN if key.repeat == 0 => {
+                            // Launch the ball, or move on to the next level or game
+                            inputs[0].launch = true;
+                        }
+
+                        keycode::SDLK_w if key.repeat == 0 => {
+                            if let Some(input) = inputs.get_mut(1) {
+                                input.launch = true;
+                            }
+                        }
+
+                        keycode::SDLK_e if key.repeat == 0 => {
+                            // Edit the board this game is played on
+                            let ed = Editor::new((*game.layout).clone());
+                            preview = World::from_level(&ed.level, aspect, options.seed);
+                            gpu.set_view(preview.bounds.x, preview.bounds.y);
+                            editor = Some(ed);
+                            println!(
+                                "Editing. Left click paints, right click erases, 1-9 picks a brick, \
+                                 Ctrl+Z/Ctrl+Y undo and redo, Ctrl+S saves to {save_path}, \
+                                 P playtests and E goes back to the game"
+                            );
+                        }
+
+                        keycode::SDLK_F5 if key.repeat == 0 => {
+                            match snapshot::save(&game, QUICKSAVE_PATH) {
+                                Ok(()) => println!("Saved to {QUICKSAVE_PATH}"),
+                                Err(e) => eprintln!("{e}"),
+                            }
+                        }
+
+                        keycode::SDLK_F9 if key.repeat == 0 => match snapshot::load(QUICKSAVE_PATH)
+                        {
+                            Ok(loaded) => {
+                                game = loaded;
+                                prev = game.clone();
+                                gpu.set_view(game.world.bounds.x, game.world.bounds.y);
+                                playback = None;
+                                history.clear();
+                                ticks_played = 0;
+                                println!("Loaded {QUICKSAVE_PATH}");
+
+                                // Replays can only start from the beginning of a game, so this is where it ends
+                                if let (Some(recording), Some(path)) =
+                                    (recording.take(), &options.record)
+                                {
+                                    match recording.save(path) {
+                                        Ok(()) => {
+                                            println!("Stopped recording, and saved it to {path}")
+                                        }
+                                        Err(e) => eprintln!("{e}"),
+                                    }
+                                }
+                            }
+                            Err(e) => eprintln!("{e}"),
+                        },
+
+                        keycode::SDLK_c => {
+                            // Clear all balls when "C" is pressed
+                            let ball_count = game.world.balls.len();
+                            inputs[0].clear_balls = true;
+                            println!("Removed {ball_count} balls");
+                        }
+
+                        _ => {}
+                    }
+                }
+
+                SDL_KEYUP => {
+                    let key = unsafe { e.key };
+
+                    match key.keysym.sym {
+                        keycode::SDLK_t if key.repeat == 0 => {
+                            assert!(capture.is_none());
+                            capture = gpu.prepare_capture();
+                        }
+
+                        _ => {}
+                    }
+                }
+                // Paint with the left button and erase with the right, dragging to do a whole stroke at once
+                SDL_MOUSEBUTTONDOWN | SDL_MOUSEMOTION => {
+                    let Some(ed) = &mut editor else {
+                        continue;
+                    };
+
+                    let (x, y, left, right) = if type_ == SDL_MOUSEBUTTONDOWN {
+                        let button = unsafe { e.button };
+                        ed.begin_stroke();
+                        let left = button.button as u32 == SDL_BUTTON_LEFT;
+                        let right = button.button as u32 == SDL_BUTTON_RIGHT;
+                        (button.x, button.y, left, right)
+                    } else {
+                        let motion = unsafe { e.motion };
+                        let left = motion.state & SDL_BUTTON_LMASK != 0;
+                        let right = motion.state & SDL_BUTTON_RMASK != 0;
+                        (motion.x, motion.y, left, right)
+                    };
+
+                    cursor = window_to_world(&window, preview.bounds, x, y);
+                    if let Some((x, y)) = ed.cell_at(preview.bounds, cursor) {
+                        if left {
+                            preview_stale |= ed.paint(x, y);
+                        } else if right {
+                            preview_stale |= ed.erase(x, y);
+                        }
+                    }
+                }
+
+                SDL_MOUSEBUTTONUP => {
+                    if let Some(ed) = &mut editor {
+                        ed.end_stroke();
+                    }
+                }
+
+                // On tap or drag, spawn a ball!
+                SDL_FINGERDOWN | SDL_FINGERMOTION => {
+                    let _tfinger: SDL_TouchFingerEvent = unsafe { e.tfinger };
+
+                    inputs[0].spawn_ball = true;
+                }
+
+                // Ignore all other events
+                _ => {}
+            }
+        }
+
+        // Simplified interface for per-frame actions that depend on a key being pressed or not.
+        let keyboard = get_keyboard_state();
+
+        // A loaded game can have a different number of players
+        inputs.resize(game.players.len(), FrameInput::default());
+        if attract || options.assist {
+            ais.truncate(game.players.len());
+            while ais.len() < game.players.len() {
+                ais.push(new_ai(ais.len()));
+            }
+        }
+
+        if keyboard[SDL_SCANCODE_B] != 0 {
+            // Spawn a ball on the paddle when "B" is pressed
+            inputs[0].spawn_ball = true;
+        }
+
+        // Update movement from events - this skips the OS keyboard delay
+        inputs[0].left = keyboard[SDL_SCANCODE_LEFT] != 0;
+        inputs[0].right = keyboard[SDL_SCANCODE_RIGHT] != 0;
+        if let Some(input) = inputs.get_mut(1) {
+            input.left = keyboard[SDL_SCANCODE_A] != 0;
+            input.right = keyboard[SDL_SCANCODE_D] != 0;
+        }
+
+        // Time goes backwards while "R" is held
+        let was_rewinding = rewinding;
+        rewinding = keyboard[SDL_SCANCODE_R] != 0;
+        if rewinding && !was_rewinding {
+            println!("Rewinding...");
+        } else if was_rewinding && !rewinding {
+            println!("Back to {:.2}s", ticks_played as f32 * timestep.dt());
+        }
+
+        // == Update gamestate =================================================
+        // Always run the clock, so time spent paused isn't all simulated at once when we unpause
+        let steps = timestep.advance(Instant::now());
+
+        if let Some(ed) = &editor {
+            if preview_stale {
+                preview = World::from_level(&ed.level, aspect, options.seed);
+                preview_stale = false;
+            }
+        }
+
+        // Advance the simulation. The game waits while we're editing.
+        if !paused && editor.is_none() {
+            // audio_player.update_waveform(|waveform| {
+            //     waveform.f -= 0.005;
+            //     if waveform.f < 0. {
+            //         waveform.f = 1.0;
+            //     }
+            // });
+
+            for _ in 0..steps {
+                if rewinding {
+                    // Stay on the oldest game we have once we run out
+                    if let Some((tick, past)) = history.pop() {
+                        std::mem::swap(&mut prev, &mut game);
+                        game = past;
+                        ticks_played = tick;
+
+                        // Whatever happened after this never happened
+                        if let Some(recording) = &mut recording {
+                            recording.truncate(ticks_played);
+                        }
+                        playback = None;
+                    }
+                    continue;
+                }
+
+                tick_inputs.clone_from(&inputs);
+                for (player, ai) in ais.iter_mut().enumerate() {
+                    // The AI keeps watching even when it isn't playing, so it's ready when it's needed
+                    let ai_input = ai.next_input(&game.world, player);
+                    let input = &mut tick_inputs[player];
+                    if attract {
+                        let between_games =
+                            matches!(game.state, GameState::LevelCleared | GameState::GameOver);
+                        *input = FrameInput {
+                            launch: ai_input.launch || between_games,
+                            ..ai_input
+                        };
+                    } else if player == 0 && !input.left && !input.right {
+                        input.left = ai_input.left;
+                        input.right = ai_input.right;
+                    }
+                }
+
+                // Replays take over from the keyboard and the AI until they run out
+                match playback.as_mut().map(Iterator::next) {
+                    Some(Some((_, true))) => continue,
+                    Some(Some((recorded, false))) => tick_inputs[0] = recorded,
+                    Some(None) => {
+                        println!("End of the replay. Back to the keyboard!");
+                        playback = None;
+                    }
+                    None => {}
+                }
+
+                if let Some(recording) = &mut recording {
+                    recording.record(&tick_inputs[0], false);
+                }
+                game.step_players_into(&tick_inputs, timestep.dt(), &mut next);
+
+                // prev <- game <- next, and the oldest game gets reused as scratch space
+                std::mem::swap(&mut prev, &mut game);
+                std::mem::swap(&mut game, &mut next);
+
+                ticks_played += 1;
+                history.record(ticks_played, &game);
+
+                // One-shot actions only happen once, held keys keep going
+                for input in &mut inputs {
+                    input.spawn_ball = false;
+                    input.clear_balls = false;
+                    input.launch = false;
+                }
+
+                if game.state != prev.state {
+                    let loser = game.serving;
+                    let lives = game.players[loser].lives;
+                    match game.state {
+                        GameState::Serve if prev.state == GameState::Playing => {
+                            match game.players.len() {
+                                1 => println!("Ball lost! {lives} lives left"),
+                                _ => println!(
+                                    "Player {} lost the ball! {lives} lives left",
+                                    loser + 1
+                                ),
+                            }
+                        }
+                        GameState::Serve => println!("Level {}", game.level),
+                        GameState::Playing => {}
+                        GameState::LevelCleared => println!(
+                            "Level {} cleared! Time bonus: {}, score: {}",
+                            game.level,
+                            game.players[0].score.last_bonus,
+                            describe_scores(&game)
+                        ),
+                        GameState::GameOver => match game.players.len() {
+                            1 => println!("Game over. Final score: {}", describe_scores(&game)),
+                            _ => println!(
+                                "Game over, player {} is out of lives. Final scores: {}",
+                                loser + 1,
+                                describe_scores(&game)
+                            ),
+                        },
+                    }
+                }
+            }
+        }
+
+        if paused && editor.is_none() {
+            if let Some(recording) = &mut recording {
+                for _ in 0..steps {
+                    recording.record(&FrameInput::default(), true);
+                }
+            }
+        }
+
+        // == Render ===========================================================
+        // Draw Quads (this is everything atm)
+        {
+            // Blend between the last two ticks, so motion stays smooth when frames and ticks don't line up
+            let alpha = if paused { 1. } else { timestep.alpha() };
+            let world = match editor {
+                Some(_) => preview.clone(),
+                None => game.world.interpolated(&prev.world, alpha),
+            };
+
+            // Balls
+            for ball in &world.balls {
+                gpu.draw_circle(ball.pos, ball.radius, color::WHITE);
+            }
+
+            // Bricks
+            for brick in &world.bricks {
+                gpu.draw_quad(brick.quad.pos, brick.quad.dims, brick.color());
+            }
+
+            // Unbreakable bricks
+            for brick in &world.unbreakable_bricks {
+                gpu.draw_quad(brick.pos, brick.dims, brick.color);
+            }
+
+            // Power-ups on their way down
+            for capsule in &world.capsules {
+                let quad = &capsule.quad;
+                gpu.draw_quad(quad.pos, quad.dims, quad.color);
+            }
+
+            // Lasers
+            for laser in &world.lasers {
+                gpu.draw_quad(laser.quad.pos, laser.quad.dims, laser.quad.color);
+            }
+
+            // Paddles
+            for (i, paddle) in world.paddles.iter().enumerate() {
+                let color = PADDLE_COLORS[i % PADDLE_COLORS.len()];
+                gpu.draw_quad(paddle.pos, paddle.dims, color);
+            }
+
+            // What the editor would paint under the mouse
+            if let Some(ed) = &editor {
+                if let Some((x, y)) = ed.cell_at(world.bounds, cursor) {
+                    let pos = ed.cell_pos(world.bounds, x, y);
+                    let dims = ed.level.brick_dims;
+                    let color = ed.palette.get(ed.selected).and_then(Cell::color);
+                    gpu.draw_quad(pos, dims, color.unwrap_or(color::WHITE));
+                }
+            }
+        }
+
+        gpu.render_and_present();
+
+        if let Some(mut c) = capture.take() {
+            c.mark_frame_done();
+
+            if c.frames_left() != 0 {
+                // oops put it back
+                capture = Some(c);
+            } else {
+                // Pause things, since we're about to switch to viewing the trace
+                paused = true;
+
+                // Finish and view the trace
+                c.stop();
+            }
+        }
+
+        // No delay here: the Metal layer hands out drawables in sync with the display, so
+        // `render_and_present()` already paces us.
+    }
+
+    if let (Some(recording), Some(path)) = (&recording, &options.record) {
+        match recording.save(path) {
+            Ok(()) => println!("Recorded to {path}"),
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+
+    unsafe {
+        SDL_Quit();
+    }
+}
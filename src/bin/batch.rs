@@ -0,0 +1,359 @@
+//! Plays a lot of games with the AI on every paddle, spread over a few threads, and reports how they went.
+//!
+//! ```text
+//! breakout-batch [--games K] [--threads N] [--seed N] [--level PATH]... [--generate SEED]...
+//!                [--mode single|co-op|versus] [--skill easy|normal|perfect] [--tick-rate HZ]
+//!                [--max-seconds S] [--stuck-seconds S] [--csv PATH] [--json PATH]
+//! ```
+//!
+//! Every level gets `--games` games, with seeds counting up from `--seed`, so the same seeds get played on
+//! each level. `--level` and `--generate` can both be given more than once, and it's the default level if
+//! neither is. Games stop when the level is cleared, the game is over, or after `--max-seconds`.
+//!
+//! For each level, this prints the spread of completion times (for games that cleared it), balls lost,
+//! bricks broken per minute, and stuck balls (ones that went `--stuck-seconds` without touching a paddle).
+//! `--csv` writes the same table to a file, and `--json` writes it along with every game's numbers.
+//!
+//! Each game plays out the same way no matter how many threads there are, so two runs with the same
+//! arguments can be diffed to see what a physics change did.
+
+use breakout::controller::Skill;
+use breakout::game::Game;
+use breakout::generate::{generate, Params};
+use breakout::level::Level;
+use breakout::rng::random_seed;
+use breakout::stats::{Autoplay, Distribution, GameStats, Outcome};
+use breakout::world::Mode;
+
+use serde::Serialize;
+
+use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+const USAGE: &str = "usage: breakout-batch [--games K] [--threads N] [--seed N] [--level PATH]... [--generate SEED]... [--mode single|co-op|versus] [--skill easy|normal|perfect] [--tick-rate HZ] [--max-seconds S] [--stuck-seconds S] [--csv PATH] [--json PATH]";
+
+/// Gets a number from a game, if the game has one
+type Metric = fn(&GameStats) -> Option<f32>;
+
+/// Everything we report the spread of. Games that don't have it are skipped.
+const METRICS: [(&str, Metric); 4] = [
+    ("completion_seconds", GameStats::completion_time),
+    ("balls_lost", |stats| Some(stats.balls_lost as f32)),
+    ("bricks_per_minute", |stats| Some(stats.bricks_per_minute())),
+    ("stuck_balls", |stats| Some(stats.stuck_balls as f32)),
+];
+
+/// Levels are told apart by where they are in the arguments, since the same name can come up twice
+#[derive(Serialize)]
+struct GameReport<'a> {
+    level_index: usize,
+    level: &'a str,
+    seed: u64,
+
+    #[serde(flatten)]
+    stats: GameStats,
+}
+
+#[derive(Serialize)]
+struct LevelReport<'a> {
+    level_index: usize,
+    level: &'a str,
+    games: usize,
+    cleared: usize,
+    game_over: usize,
+    timed_out: usize,
+    metrics: Vec<MetricReport>,
+}
+
+#[derive(Serialize)]
+struct MetricReport {
+    metric: &'static str,
+
+    /// Missing when none of the games had this, like completion times when nothing got cleared
+    #[serde(flatten)]
+    distribution: Option<Distribution>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    levels: Vec<LevelReport<'a>>,
+    games: Vec<GameReport<'a>>,
+}
+
+/// `text` as a single CSV field, quoted if it has anything in it that would split it up
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn main() {
+    let mut games_per_level: usize = 16;
+    let mut threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut seed = None;
+    let mut levels: Vec<(String, Level)> = vec![];
+    let mut mode = Mode::Single;
+    let mut autoplay = Autoplay::default();
+    let mut csv_path = None;
+    let mut json_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().unwrap_or_else(|| {
+                eprintln!("Missing value for {arg}");
+                eprintln!("{USAGE}");
+                exit(1);
+            })
+        };
+
+        match arg.as_str() {
+            "--games" => {
+                games_per_level = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --games: {e}");
+                    exit(1);
+                });
+            }
+            "--threads" => {
+                threads = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --threads: {e}");
+                    exit(1);
+                });
+            }
+            "--seed" => {
+                seed = Some(value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --seed: {e}");
+                    exit(1);
+                }));
+            }
+            "--level" => {
+                let path = value();
+                let level = Level::load(&path).unwrap_or_else(|e| {
+                    eprintln!("{e}");
+                    exit(1);
+                });
+                levels.push((path, level));
+            }
+            "--generate" => {
+                let level_seed: u64 = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --generate: {e}");
+                    exit(1);
+                });
+                levels.push((
+                    format!("generated-{level_seed}"),
+                    generate(level_seed, &Params::default()),
+                ));
+            }
+            "--mode" => {
+                let name = value();
+                mode = Mode::from_name(&name).unwrap_or_else(|| {
+                    eprintln!("Unknown mode \"{name}\"");
+                    eprintln!("{USAGE}");
+                    exit(1);
+                });
+            }
+            "--skill" => {
+                let name = value();
+                autoplay.skill = Skill::from_name(&name).unwrap_or_else(|| {
+                    eprintln!("Unknown skill \"{name}\"");
+                    eprintln!("{USAGE}");
+                    exit(1);
+                });
+            }
+            "--tick-rate" => {
+                autoplay.tick_rate = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --tick-rate: {e}");
+                    exit(1);
+                });
+                if !autoplay.tick_rate.is_finite() || autoplay.tick_rate <= 0. {
+                    eprintln!(
+                        "Bad value for --tick-rate: {} isn't a positive number of Hz",
+                        autoplay.tick_rate
+                    );
+                    exit(1);
+                }
+            }
+            "--max-seconds" => {
+                autoplay.max_seconds = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --max-seconds: {e}");
+                    exit(1);
+                });
+            }
+            "--stuck-seconds" => {
+                autoplay.stuck_seconds = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --stuck-seconds: {e}");
+                    exit(1);
+                });
+            }
+            "--csv" => {
+                csv_path = Some(value());
+            }
+            "--json" => {
+                json_path = Some(value());
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ => {
+                eprintln!("Unknown argument \"{arg}\"");
+                eprintln!("{USAGE}");
+                exit(1);
+            }
+        }
+    }
+
+    if levels.is_empty() {
+        levels.push(("default".to_string(), Level::default_level()));
+    }
+    let seed: u64 = seed.unwrap_or_else(random_seed);
+    let last_seed = seed.wrapping_add(games_per_level.saturating_sub(1) as u64);
+    println!("Seeds: {seed} to {last_seed}");
+
+    let aspect = breakout::WINDOW_HEIGHT as f32 / breakout::WINDOW_WIDTH as f32;
+    let layouts: Vec<_> = levels
+        .iter()
+        .map(|(_, level)| Arc::new(level.clone()))
+        .collect();
+
+    // Every level with every seed, handed out to whichever thread is free next
+    let jobs: Vec<_> = (0..levels.len())
+        .flat_map(|level| (0..games_per_level).map(move |i| (level, seed.wrapping_add(i as u64))))
+        .collect();
+    let next_job = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; jobs.len()]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let job = next_job.fetch_add(1, Ordering::Relaxed);
+                let Some(&(level, seed)) = jobs.get(job) else {
+                    break;
+                };
+
+                let game = Game::from_level_with(layouts[level].clone(), aspect, seed, mode);
+                let stats = autoplay.play(game, seed);
+                results.lock().unwrap()[job] = Some(stats);
+            });
+        }
+    });
+
+    let results: Vec<_> = results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect();
+    let games: Vec<_> = jobs
+        .iter()
+        .zip(results)
+        .map(|(&(level, seed), stats)| GameReport {
+            level_index: level,
+            level: &levels[level].0,
+            seed,
+            stats,
+        })
+        .collect();
+
+    let reports: Vec<_> = levels
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _))| {
+            let played: Vec<_> = games
+                .iter()
+                .filter(|game| game.level_index == index)
+                .collect();
+            let outcomes = |outcome| {
+                let ended = |game: &&&GameReport| game.stats.outcome == outcome;
+                played.iter().filter(ended).count()
+            };
+
+            LevelReport {
+                level_index: index,
+                level: name,
+                games: played.len(),
+                cleared: outcomes(Outcome::Cleared),
+                game_over: outcomes(Outcome::GameOver),
+                timed_out: outcomes(Outcome::TimedOut),
+                metrics: METRICS
+                    .iter()
+                    .map(|(metric, get)| {
+                        let values = played.iter().filter_map(|game| get(&game.stats));
+                        MetricReport {
+                            metric,
+                            distribution: Distribution::of(values),
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    for report in &reports {
+        println!();
+        println!(
+            "{}: {} games, {} cleared, {} game over, {} timed out",
+            report.level, report.games, report.cleared, report.game_over, report.timed_out
+        );
+        println!(
+            "  {:<20}{:>7}{:>9}{:>9}{:>9}{:>9}{:>9}{:>9}",
+            "", "count", "mean", "min", "p10", "median", "p90", "max"
+        );
+        for MetricReport {
+            metric,
+            distribution,
+        } in &report.metrics
+        {
+            match distribution {
+                Some(d) => println!(
+                    "  {metric:<20}{:>7}{:>9.2}{:>9.2}{:>9.2}{:>9.2}{:>9.2}{:>9.2}",
+                    d.count, d.mean, d.min, d.p10, d.median, d.p90, d.max
+                ),
+                None => println!("  {metric:<20}{:>7}", 0),
+            }
+        }
+    }
+
+    if let Some(path) = csv_path {
+        let mut csv = "level_index,level,metric,count,mean,min,p10,median,p90,max\n".to_string();
+        for report in &reports {
+            for MetricReport {
+                metric,
+                distribution,
+            } in &report.metrics
+            {
+                let columns = match distribution {
+                    Some(d) => format!(
+                        "{},{},{},{},{},{},{}",
+                        d.count, d.mean, d.min, d.p10, d.median, d.p90, d.max
+                    ),
+                    None => "0,,,,,,".to_string(),
+                };
+                let level = csv_field(report.level);
+                csv += &format!("{},{level},{metric},{columns}\n", report.level_index);
+            }
+        }
+
+        std::fs::write(&path, csv).unwrap_or_else(|e| {
+            eprintln!("Couldn't write {path}: {e}");
+            exit(1);
+        });
+        println!("\nWrote {path}");
+    }
+
+    if let Some(path) = json_path {
+        let report = Report {
+            levels: reports,
+            games,
+        };
+        let json = serde_json::to_string_pretty(&report).expect("Reports should always serialize");
+        std::fs::write(&path, json).unwrap_or_else(|e| {
+            eprintln!("Couldn't write {path}: {e}");
+            exit(1);
+        });
+        println!("\nWrote {path}");
+    }
+}
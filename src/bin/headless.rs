@@ -0,0 +1,397 @@
+//! Runs the simulation without a window, GPU, or audio, and prints a summary when it's done.
+//!
+//! ```text
+//! breakout-headless [--ticks N] [--tick-rate HZ] [--seed N] [--level PATH | --generate SEED]
+//!                   [--mode single|co-op|versus] [--input idle|random|sweep|ai] [--skill easy|normal|perfect]
+//!                   [--script "L*40 R*40 B .*10"] [--record PATH]
+//! breakout-headless --replay PATH
+//! ```
+//!
+//! `--generate` plays a board made up from its own seed (see [`breakout::generate`]) instead of a level file.
+//!
+//! `--mode co-op` and `--mode versus` add a second player, who gets their own copy of the same kind of input.
+//!
+//! `--input ai` plays with [`breakout::controller::Ai`], as well as `--skill` says it can.
+//!
+//! The same `--seed` with the same input always plays out the same way, including `--input random`.
+//!
+//! Scripts are a list of `<keys>*<ticks>` steps that loop until the run ends. Keys are
+//! `L` (left), `R` (right), `B` (spawn a ball), `C` (clear balls), `S` (launch), and `.` (nothing).
+//! `*<ticks>` is optional and defaults to a single tick. This is the same as the inputs in a replay file
+//! (see [`breakout::replay`]), except scripts can't pause.
+//!
+//! `--record` saves every tick's input to a replay file when the run ends. `--replay` plays one back,
+//! with the seed, level and tick rate it was recorded with, until its inputs run out. Replays are single
+//! player only.
+//!
+//! Inputs that never launch have it done for them, so every serve gets played. The run ends early when
+//! the level is cleared or the game is over, except for replays, which go on until their inputs run out.
+
+use breakout::controller::{Ai, PaddleController, Skill};
+use breakout::game::{Game, GameState, Player};
+use breakout::generate::{generate, Params};
+use breakout::level::Level;
+use breakout::replay::{parse_runs, Replay};
+use breakout::rng::{random_seed, Pcg32};
+use breakout::timestep::DEFAULT_TICK_RATE;
+use breakout::world::{Event, FrameInput, Mode, World};
+
+use std::process::exit;
+use std::sync::Arc;
+
+const USAGE: &str = "usage: breakout-headless [--ticks N] [--tick-rate HZ] [--seed N] [--level PATH | --generate SEED] [--mode single|co-op|versus] [--input idle|random|sweep|ai] [--skill easy|normal|perfect] [--script \"L*40 R*40\"] [--record PATH] | --replay PATH";
+
+/// Stream for the random input, so it doesn't share its sequence with the world's rng
+const INPUT_RNG_STREAM: u64 = 1;
+
+#[derive(Clone)]
+enum PaddleInput {
+    /// Never touch anything
+    Idle,
+
+    /// Hold a random direction for a random number of ticks, then pick again
+    Random { held: FrameInput, ticks_left: u32 },
+
+    /// Slide all the way left, then all the way right, forever
+    Sweep { going_left: bool },
+
+    /// Loop through a fixed list of inputs
+    Script {
+        steps: Vec<(FrameInput, u32)>,
+        step: usize,
+        ticks_left: u32,
+    },
+
+    /// Play back a recording, and do nothing once it's over
+    Replay {
+        inputs: std::vec::IntoIter<FrameInput>,
+    },
+
+    /// Let the AI play
+    Ai(Ai),
+}
+
+impl PaddleInput {
+    /// Input for the player with this `paddle`
+    fn next(&mut self, world: &World, paddle: usize, rng: &mut Pcg32) -> FrameInput {
+        match self {
+            PaddleInput::Idle => FrameInput::default(),
+
+            PaddleInput::Random { held, ticks_left } => {
+                use rand::prelude::*;
+
+                if *ticks_left == 0 {
+                    let dir: i32 = rng.random_range(-1..=1);
+                    *held = FrameInput {
+                        left: dir < 0,
+                        right: dir > 0,
+                        ..Default::default()
+                    };
+                    *ticks_left = rng.random_range(10..200);
+                }
+                *ticks_left -= 1;
+
+                *held
+            }
+
+            PaddleInput::Sweep { going_left } => {
+                let (left, right) = world.paddle_lane(paddle);
+                let paddle = &world.paddles[paddle];
+                if paddle.pos.x <= left {
+                    *going_left = false;
+                } else if paddle.pos.x + paddle.dims.x >= right {
+                    *going_left = true;
+                }
+
+                FrameInput {
+                    left: *going_left,
+                    right: !*going_left,
+                    ..Default::default()
+                }
+            }
+
+            PaddleInput::Script {
+                steps,
+                step,
+                ticks_left,
+            } => {
+                while *ticks_left == 0 {
+                    *step = (*step + 1) % steps.len();
+                    *ticks_left = steps[*step].1;
+                }
+                *ticks_left -= 1;
+
+                steps[*step].0
+            }
+
+            PaddleInput::Replay { inputs } => inputs.next().unwrap_or_default(),
+
+            PaddleInput::Ai(ai) => ai.next_input(world, paddle),
+        }
+    }
+
+    /// Whether this input ever launches the ball
+    fn launches(&self) -> bool {
+        match self {
+            PaddleInput::Script { steps, .. } => steps.iter().any(|(input, _)| input.launch),
+            PaddleInput::Replay { .. } | PaddleInput::Ai(_) => true,
+            _ => false,
+        }
+    }
+}
+
+fn parse_script(script: &str) -> Result<PaddleInput, String> {
+    let runs = parse_runs(script.split_whitespace())?;
+    if runs.iter().any(|run| run.paused) {
+        return Err("Scripts can't pause".to_string());
+    }
+
+    let steps: Vec<_> = runs.iter().map(|run| (run.input, run.ticks)).collect();
+    if steps.is_empty() {
+        return Err("Script doesn't have any steps".to_string());
+    }
+
+    let ticks_left = steps[0].1;
+    Ok(PaddleInput::Script {
+        steps,
+        step: 0,
+        ticks_left,
+    })
+}
+
+fn main() {
+    let mut ticks: u64 = 60 * DEFAULT_TICK_RATE as u64;
+    let mut tick_rate = DEFAULT_TICK_RATE;
+    let mut seed = None;
+    let mut layout = None;
+    let mut record_path = None;
+    let mut replay = None;
+    let mut mode = Mode::Single;
+    let mut use_ai = false;
+    let mut skill = Skill::default();
+    let mut paddle_input = PaddleInput::Random {
+        held: FrameInput::default(),
+        ticks_left: 0,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().unwrap_or_else(|| {
+                eprintln!("Missing value for {arg}");
+                eprintln!("{USAGE}");
+                exit(1);
+            })
+        };
+
+        match arg.as_str() {
+            "--ticks" => {
+                ticks = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --ticks: {e}");
+                    exit(1);
+                });
+            }
+            "--tick-rate" => {
+                tick_rate = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --tick-rate: {e}");
+                    exit(1);
+                });
+                if !tick_rate.is_finite() || tick_rate <= 0. {
+                    eprintln!(
+                        "Bad value for --tick-rate: {tick_rate} isn't a positive number of Hz"
+                    );
+                    exit(1);
+                }
+            }
+            "--seed" => {
+                seed = Some(value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --seed: {e}");
+                    exit(1);
+                }));
+            }
+            "--level" => {
+                layout = Some(Level::load(value()).unwrap_or_else(|e| {
+                    eprintln!("{e}");
+                    exit(1);
+                }));
+            }
+            "--generate" => {
+                let level_seed = value().parse().unwrap_or_else(|e| {
+                    eprintln!("Bad value for --generate: {e}");
+                    exit(1);
+                });
+                layout = Some(generate(level_seed, &Params::default()));
+            }
+            "--mode" => {
+                let name = value();
+                mode = Mode::from_name(&name).unwrap_or_else(|| {
+                    eprintln!("Unknown mode \"{name}\"");
+                    eprintln!("{USAGE}");
+                    exit(1);
+                });
+            }
+            "--input" => {
+                let name = value();
+                use_ai = name == "ai";
+                paddle_input = match name.as_str() {
+                    // The AI needs the seed and tick rate, so it's made once we have those
+                    "ai" => PaddleInput::Idle,
+                    "idle" => PaddleInput::Idle,
+                    "random" => PaddleInput::Random {
+                        held: FrameInput::default(),
+                        ticks_left: 0,
+                    },
+                    "sweep" => PaddleInput::Sweep { going_left: true },
+                    other => {
+                        eprintln!("Unknown input \"{other}\"");
+                        eprintln!("{USAGE}");
+                        exit(1);
+                    }
+                };
+            }
+            "--skill" => {
+                let name = value();
+                skill = Skill::from_name(&name).unwrap_or_else(|| {
+                    eprintln!("Unknown skill \"{name}\"");
+                    eprintln!("{USAGE}");
+                    exit(1);
+                });
+            }
+            "--script" => {
+                use_ai = false;
+                paddle_input = parse_script(&value()).unwrap_or_else(|e| {
+                    eprintln!("Bad --script: {e}");
+                    exit(1);
+                });
+            }
+            "--record" => {
+                record_path = Some(value());
+            }
+            "--replay" => {
+                replay = Some(Replay::load(value()).unwrap_or_else(|e| {
+                    eprintln!("{e}");
+                    exit(1);
+                }));
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ => {
+                eprintln!("Unknown argument \"{arg}\"");
+                eprintln!("{USAGE}");
+                exit(1);
+            }
+        }
+    }
+
+    let mut aspect = breakout::WINDOW_HEIGHT as f32 / breakout::WINDOW_WIDTH as f32;
+
+    if mode != Mode::Single && (replay.is_some() || record_path.is_some()) {
+        eprintln!("Replays only have room for one player");
+        exit(1);
+    }
+
+    // A replay brings everything it needs to play out the same way again
+    let replaying = replay.is_some();
+    if let Some(replay) = replay {
+        aspect = replay.aspect;
+        seed = Some(replay.seed);
+        tick_rate = replay.tick_rate;
+        ticks = replay.played_ticks();
+        use_ai = false;
+        let inputs = replay.inputs().filter(|(_, paused)| !paused);
+        paddle_input = PaddleInput::Replay {
+            inputs: inputs
+                .map(|(input, _)| input)
+                .collect::<Vec<_>>()
+                .into_iter(),
+        };
+        layout = Some(replay.level);
+    }
+
+    let seed = seed.unwrap_or_else(random_seed);
+    println!("Seed: {seed}");
+
+    if use_ai {
+        paddle_input = PaddleInput::Ai(Ai::new(skill, tick_rate, seed));
+    }
+
+    let layout = layout.unwrap_or_else(Level::default_level);
+    let mut recording = record_path
+        .as_ref()
+        .map(|_| Replay::new(layout.clone(), aspect, seed, tick_rate));
+    let mut game = Game::from_level_with(Arc::new(layout), aspect, seed, mode);
+    let mut input_rng = Pcg32::new_with_stream(seed, INPUT_RNG_STREAM);
+    let mut next = Game::default();
+    let auto_launch = !paddle_input.launches();
+    let mut paddle_inputs = vec![paddle_input; game.players.len()];
+    let mut inputs = vec![];
+
+    // No clock to keep up with here, just run the ticks back to back
+    let dt = 1. / tick_rate;
+
+    let brick_count = game.world.bricks.len();
+    let mut balls_lost = 0;
+    let mut ticks_elapsed = 0;
+
+    while ticks_elapsed < ticks {
+        inputs.clear();
+        for (paddle, paddle_input) in paddle_inputs.iter_mut().enumerate() {
+            inputs.push(paddle_input.next(&game.world, paddle, &mut input_rng));
+        }
+        inputs[game.serving].launch |= auto_launch && game.state == GameState::Serve;
+        if let Some(recording) = &mut recording {
+            recording.record(&inputs[0], false);
+        }
+
+        game.step_players_into(&inputs, dt, &mut next);
+        std::mem::swap(&mut game, &mut next);
+        ticks_elapsed += 1;
+
+        balls_lost += game
+            .world
+            .events
+            .iter()
+            .filter(|e| matches!(e, Event::BallLost { .. }))
+            .count();
+
+        // Nothing else is going to happen without someone to move on, so don't bother simulating it. Replays
+        // can move on themselves, so they play out to the end.
+        let over = matches!(game.state, GameState::LevelCleared | GameState::GameOver);
+        if over && !replaying {
+            break;
+        }
+    }
+
+    println!(
+        "Ticks elapsed: {ticks_elapsed} ({:.2}s)",
+        ticks_elapsed as f32 * dt
+    );
+    println!("Game state:    {:?}", game.state);
+    let print_player = |player: &Player| {
+        println!("Lives left:    {}", player.lives);
+        println!("Score:         {}", player.score.points);
+        println!("Best combo:    {}", player.score.best_combo);
+    };
+    match &game.players[..] {
+        [player] => print_player(player),
+        players => {
+            for (i, player) in players.iter().enumerate() {
+                println!("Player {}:", i + 1);
+                print_player(player);
+            }
+        }
+    }
+    println!("Bricks left:   {} / {brick_count}", game.world.bricks.len());
+    println!("Balls lost:    {balls_lost}");
+    println!("Balls in play: {}", game.world.balls.len());
+
+    if let (Some(recording), Some(path)) = (recording, record_path) {
+        recording.save(&path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            exit(1);
+        });
+        println!("Recorded to {path}");
+    }
+}
@@ -0,0 +1,326 @@
+//! Things that can play a paddle instead of a person.
+//!
+//! Anything that turns a [`World`] into a [`FrameInput`] every tick can play, with [`PaddleController`]. The
+//! one built in here is [`Ai`], which works out where the next ball is going to come down and goes there.
+
+use rand::prelude::*;
+use ultraviolet::Vec2;
+
+use std::collections::VecDeque;
+
+use crate::math::{Aabb, HitInfo};
+use crate::rng::Pcg32;
+use crate::world::{Ball, FrameInput, World};
+
+/// Plays one of the paddles in a world
+pub trait PaddleController {
+    /// Input for whoever has `paddle`, for the next step of `world`. This gets called once every tick.
+    fn next_input(&mut self, world: &World, paddle: usize) -> FrameInput;
+}
+
+/// How good an [`Ai`] is at the game
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Skill {
+    /// Seconds it takes to react. It always acts on where it thought balls were going this long ago.
+    pub reaction_delay: f32,
+
+    /// Furthest its guess at where a ball comes down can be off, in world units. It guesses again every
+    /// time the ball changes direction.
+    pub prediction_noise: f32,
+}
+
+impl Skill {
+    pub const EASY: Skill = Skill {
+        reaction_delay: 0.2,
+        prediction_noise: 6.,
+    };
+
+    pub const NORMAL: Skill = Skill {
+        reaction_delay: 0.12,
+        prediction_noise: 4.,
+    };
+
+    /// Never misses, unless the ball is too fast to catch
+    pub const PERFECT: Skill = Skill {
+        reaction_delay: 0.,
+        prediction_noise: 0.,
+    };
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Skill::EASY),
+            "normal" => Some(Skill::NORMAL),
+            "perfect" => Some(Skill::PERFECT),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Skill::NORMAL
+    }
+}
+
+/// Plays a paddle by tracing where the closest ball is going to cross in front of it, bouncing off of walls
+/// and bricks on the way, and sliding over there.
+///
+/// With nothing headed its way it goes back to the middle of its lane. Balls held on its paddle get launched
+/// right away.
+#[derive(Clone, Debug)]
+pub struct Ai {
+    pub skill: Skill,
+
+    /// Seconds per tick
+    dt: f32,
+
+    /// Where it guessed a ball would come down on each of the last few ticks, oldest first
+    guesses: VecDeque<Option<f32>>,
+
+    /// How far off the current guess is, and the ball (and which way it was going) that it's for
+    noise: f32,
+    noisy_ball: Option<(u32, Vec2)>,
+
+    rng: Pcg32,
+}
+
+impl Ai {
+    /// An AI for a game that ticks at `tick_rate`. `seed` picks how its guesses are off.
+    pub fn new(skill: Skill, tick_rate: f32, seed: u64) -> Self {
+        Self {
+            skill,
+            dt: 1. / tick_rate,
+            guesses: VecDeque::new(),
+            noise: 0.,
+            noisy_ball: None,
+            rng: Pcg32::new(seed),
+        }
+    }
+
+    /// Where `ball` comes down, give or take the prediction noise
+    fn guess(&mut self, ball: &Ball, landing: f32) -> f32 {
+        let heading = Vec2::new(ball.vel.x.signum(), ball.vel.y.signum());
+        if self.noisy_ball != Some((ball.id, heading)) {
+            self.noisy_ball = Some((ball.id, heading));
+
+            let max = self.skill.prediction_noise;
+            self.noise = if max > 0. {
+                self.rng.random_range(-max..=max)
+            } else {
+                0.
+            };
+        }
+
+        landing + self.noise
+    }
+}
+
+impl PaddleController for Ai {
+    fn next_input(&mut self, world: &World, paddle: usize) -> FrameInput {
+        let guess = predict_landing(world, paddle).map(|(ball, x)| self.guess(ball, x));
+
+        // Act on what we saw `reaction_delay` ago
+        let delay = (self.skill.reaction_delay / self.dt).round() as usize;
+        self.guesses.push_back(guess);
+        while self.guesses.len() > delay + 1 {
+            self.guesses.pop_front();
+        }
+        let target = match self.guesses.front().copied().flatten() {
+            Some(x) => x,
+            None => {
+                let (left, right) = world.paddle_lane(paddle);
+                0.5 * (left + right)
+            }
+        };
+
+        // Close enough is anything we'd overshoot by moving for another tick
+        let quad = &world.paddles[paddle];
+        let center = quad.pos.x + 0.5 * quad.dims.x;
+        let close_enough = 0.5 * world.rules.paddle_speed * self.dt;
+        let held = |ball: &Ball| ball.stuck.is_some_and(|stuck| stuck.paddle == paddle);
+
+        FrameInput {
+            left: center - target > close_enough,
+            right: target - center > close_enough,
+            launch: world.balls.iter().any(held),
+            ..Default::default()
+        }
+    }
+}
+
+/// Most walls and bricks we follow a ball off of when predicting where it goes
+const MAX_PREDICTED_BOUNCES: usize = 16;
+
+/// The ball that's going to reach one of the world's `paddles` first, and the x where its center crosses in
+/// front of the paddle.
+///
+/// Balls heading for the paddle come first, closest first. If none are, it's the closest one, wherever it
+/// comes back down. Other paddles and balls are ignored, and bricks never break along the way.
+pub fn predict_landing(world: &World, paddle: usize) -> Option<(&Ball, f32)> {
+    let quad = &world.paddles[paddle];
+    let facing = world.paddle_facing(paddle);
+    let front = if facing > 0. {
+        quad.pos.y + quad.dims.y
+    } else {
+        quad.pos.y
+    };
+
+    let distance = |ball: &&Ball| (ball.pos.y - front) * facing;
+    let incoming = |ball: &&Ball| ball.vel.y * facing < 0.;
+    let in_play = world
+        .balls
+        .iter()
+        .filter(|ball| ball.stuck.is_none() && distance(ball) > 0.);
+    let ball = in_play.min_by(|a, b| {
+        // `false` sorts first, so that's balls on their way in
+        (!incoming(a))
+            .cmp(&!incoming(b))
+            .then_with(|| distance(a).total_cmp(&distance(b)))
+    })?;
+
+    let line = front + facing * ball.radius;
+    trace(world, ball, line).map(|x| (ball, x))
+}
+
+/// Follow `ball` in a straight line from bounce to bounce, until it crosses `y = line`. Returns the x where it
+/// crosses, or `None` if it gets away first.
+fn trace(world: &World, ball: &Ball, line: f32) -> Option<f32> {
+    let (mut pos, mut vel) = (ball.pos, ball.vel);
+
+    let mut nearby = vec![];
+
+    for _ in 0..MAX_PREDICTED_BOUNCES {
+        // Head straight for the line if we're going that way. Otherwise go as far as the edge of the playfield,
+        // where there's either a wall to bounce off of next time around, or nothing and the ball is gone.
+        let t_line = (line - pos.y) / vel.y;
+        let towards_line = t_line.is_finite() && t_line > 0.;
+        let motion = if towards_line {
+            t_line * vel
+        } else {
+            let edge = if vel.y > 0. { world.bounds.y } else { 0. };
+            let t_edge = (edge - pos.y) / vel.y;
+            if !t_edge.is_finite() || t_edge <= 0. {
+                return None;
+            }
+            t_edge * vel
+        };
+
+        let swept = Aabb::new_from_quad(pos, motion).padded(Vec2::broadcast(ball.radius));
+        world.brick_grid.query(&swept, &mut nearby);
+
+        // Same ids as `World::brick_grid`
+        let mut first: Option<HitInfo> = None;
+        for &id in &nearby {
+            let id = id as usize;
+            let quad = match world.bricks.get(id) {
+                Some(brick) => &brick.quad,
+                None => &world.unbreakable_bricks[id - world.bricks.len()],
+            };
+            let hit = Aabb::new_from_quad(quad.pos, quad.dims).intersects_with_circle_sweep(
+                pos,
+                ball.radius,
+                motion,
+            );
+            if let Some(hit) = hit {
+                if first.is_none_or(|first| hit.t < first.t) {
+                    first = Some(hit);
+                }
+            }
+        }
+
+        match first {
+            Some(hit) => {
+                pos = hit.pos;
+                vel = vel.reflected(hit.normal);
+            }
+            None if towards_line => return Some((pos + motion).x),
+            None => pos += motion,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::game::{Game, GameState, STARTING_LIVES};
+    use crate::level::Level;
+    use pretty_assertions::assert_eq;
+
+    const TICK_RATE: f32 = 200.;
+
+    #[test]
+    fn check_prediction_off_the_walls() {
+        let mut world = World::new(1.5, 0);
+        world.bricks.clear();
+        world.rebuild_brick_grid();
+        let paddle = world.paddles[0];
+        let line = paddle.pos.y + paddle.dims.y + 0.5;
+
+        // Straight down
+        world.balls[0].pos = Vec2::new(30., 50.);
+        world.balls[0].vel = Vec2::new(0., -100.);
+        let (_, x) = predict_landing(&world, 0).unwrap();
+        assert!((x - 30.).abs() < 1e-3, "{x}");
+
+        // Off the left wall, which is at x = 0
+        world.balls[0].vel = Vec2::new(-100., -100.);
+        let (_, x) = predict_landing(&world, 0).unwrap();
+        let expected = 0.5 + (50. - (30. - 0.5) - line);
+        assert!((x - expected).abs() < 1e-3, "{x} should be {expected}");
+
+        // Up, off the ceiling and back down
+        world.balls[0].vel = Vec2::new(0., 100.);
+        let (_, x) = predict_landing(&world, 0).unwrap();
+        assert!((x - 30.).abs() < 1e-3, "{x}");
+    }
+
+    #[test]
+    fn check_prediction_off_bricks() {
+        let level = Level::parse("key R brick red 1 1 0\nboard\nRRRRR\n").unwrap();
+        let mut world = World::from_level(&level, 3., 0);
+        let brick = world.bricks[2].quad;
+
+        // Straight up into the middle brick comes straight back down
+        world.balls[0].pos = brick.pos + Vec2::new(0.5 * brick.dims.x, -10.);
+        world.balls[0].vel = Vec2::new(0., 100.);
+        let (_, x) = predict_landing(&world, 0).unwrap();
+        assert!((x - world.balls[0].pos.x).abs() < 1e-3, "{x}");
+
+        // Lower balls on their way down come first
+        let mut falling = world.balls[0];
+        falling.id = 1;
+        falling.pos = Vec2::new(3., 0.5 * world.bounds.y);
+        falling.vel = Vec2::new(0., -100.);
+        world.balls.push(falling);
+        let (ball, x) = predict_landing(&world, 0).unwrap();
+        assert_eq!(ball.id, 1);
+        assert!((x - 3.).abs() < 1e-3, "{x}");
+    }
+
+    #[test]
+    fn check_skill_matters() {
+        // Play until the first ball is lost, and see how long that took
+        let survive = |skill: Skill| {
+            let mut ai = Ai::new(skill, TICK_RATE, 7);
+            let mut game = Game::new(1.5, 0xb0a7);
+            for tick in 0..20_000 {
+                let input = ai.next_input(&game.world, 0);
+                game = game.step(&input, 1. / TICK_RATE);
+                if game.players[0].lives < STARTING_LIVES || game.state == GameState::LevelCleared {
+                    return tick;
+                }
+            }
+            20_000
+        };
+
+        let perfect = survive(Skill::PERFECT);
+        let easy = survive(Skill::EASY);
+        assert!(
+            perfect > 2_000,
+            "The perfect AI only lasted {perfect} ticks"
+        );
+        assert!(perfect > easy, "perfect {perfect} vs. easy {easy}");
+    }
+}
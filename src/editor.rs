@@ -0,0 +1,256 @@
+use ultraviolet::Vec2;
+
+use crate::level::{Cell, Level};
+
+/// Edits a [`Level`] one cell at a time, with undo and redo.
+///
+/// This doesn't know anything about the mouse or the screen, so the app turns clicks into cells with
+/// [`Editor::cell_at()`] and calls [`Editor::paint()`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Editor {
+    pub level: Level,
+
+    /// What can be painted. The app picks from these with the number keys.
+    pub palette: Vec<Cell>,
+
+    /// Index into `palette` of what left clicks paint
+    pub selected: usize,
+
+    /// Strokes we can undo, with the latest at the end
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+
+    /// New edits join the last stroke instead of starting their own
+    in_stroke: bool,
+}
+
+/// One cell changing
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Edit {
+    index: usize,
+    before: Cell,
+    after: Cell,
+}
+
+impl Editor {
+    /// Start editing `level`. The palette has every kind of brick in the default level, a wall, and whatever
+    /// else `level` uses.
+    pub fn new(level: Level) -> Self {
+        let mut palette = vec![];
+        let default_level = Level::default_level();
+        let cells = default_level
+            .cells
+            .iter()
+            .chain([&Cell::Wall(crate::UNBREAKABLE_BRICK_COLOR)])
+            .chain(&level.cells);
+        for cell in cells {
+            if *cell != Cell::Empty && !palette.contains(cell) {
+                palette.push(*cell);
+            }
+        }
+
+        Self {
+            level,
+            palette,
+            ..Default::default()
+        }
+    }
+
+    /// The grid cell under `pos`, for a world built from this level with [`World::from_level()`].
+    ///
+    /// Each cell owns its brick and the gaps above and to the right of it, so there's no dead space between
+    /// them. `bounds` is the world's `bounds`.
+    ///
+    /// [`World::from_level()`]: crate::world::World::from_level
+    pub fn cell_at(&self, bounds: Vec2, pos: Vec2) -> Option<(usize, usize)> {
+        let level = &self.level;
+        let dims = level.brick_dims;
+
+        // Undo `pos_x = (dims.x + 1.) * x + 1.` and `pos_y = view_y - (dims.y + 1.) * (y + 1.)`
+        let x = ((pos.x - 1.) / (dims.x + 1.)).floor();
+        let y = ((bounds.y - pos.y) / (dims.y + 1.)).floor();
+
+        let in_grid = (0. <= x && x < level.columns as f32) && (0. <= y && y < level.rows as f32);
+        in_grid.then_some((x as usize, y as usize))
+    }
+
+    /// Bottom left corner of the brick in cell `(x, y)`, the other way around from [`Editor::cell_at()`]
+    pub fn cell_pos(&self, bounds: Vec2, x: usize, y: usize) -> Vec2 {
+        let dims = self.level.brick_dims;
+
+        Vec2::new(
+            (dims.x + 1.) * x as f32 + 1.,
+            bounds.y - (dims.y + 1.) * (y as f32 + 1.),
+        )
+    }
+
+    /// Paint the selected cell from the palette at `(x, y)`. Painting over a brick recolors it.
+    ///
+    /// Returns false if nothing changed.
+    pub fn paint(&mut self, x: usize, y: usize) -> bool {
+        match self.palette.get(self.selected) {
+            Some(cell) => self.set(x, y, *cell),
+            None => false,
+        }
+    }
+
+    /// Returns false if there was nothing to erase
+    pub fn erase(&mut self, x: usize, y: usize) -> bool {
+        self.set(x, y, Cell::Empty)
+    }
+
+    /// Change one cell. Returns false if it was already `cell`, and nothing changed.
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) -> bool {
+        let index = y * self.level.columns + x;
+        let before = self.level.cells[index];
+        if before == cell {
+            return false;
+        }
+
+        self.level.cells[index] = cell;
+        let edit = Edit {
+            index,
+            before,
+            after: cell,
+        };
+        match self.undo.last_mut() {
+            Some(stroke) if self.in_stroke => stroke.push(edit),
+            _ => self.undo.push(vec![edit]),
+        }
+        self.redo.clear();
+
+        true
+    }
+
+    /// Group every edit from now until [`Editor::end_stroke()`] into a single undo, like a drag of the mouse
+    pub fn begin_stroke(&mut self) {
+        self.end_stroke();
+        self.undo.push(vec![]);
+        self.in_stroke = true;
+    }
+
+    pub fn end_stroke(&mut self) {
+        if self.in_stroke && self.undo.last().is_some_and(|stroke| stroke.is_empty()) {
+            self.undo.pop();
+        }
+        self.in_stroke = false;
+    }
+
+    /// Take back the last stroke. Returns false if there's nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.end_stroke();
+        let Some(stroke) = self.undo.pop() else {
+            return false;
+        };
+
+        for edit in stroke.iter().rev() {
+            self.level.cells[edit.index] = edit.before;
+        }
+        self.redo.push(stroke);
+
+        true
+    }
+
+    /// Put back the last stroke that was undone. Returns false if there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.end_stroke();
+        let Some(stroke) = self.redo.pop() else {
+            return false;
+        };
+
+        for edit in &stroke {
+            self.level.cells[edit.index] = edit.after;
+        }
+        self.undo.push(stroke);
+
+        true
+    }
+
+    /// Write the level to `path`
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        std::fs::write(path, self.level.to_text())
+            .map_err(|e| format!("Couldn't write {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::world::World;
+    use pretty_assertions::assert_eq;
+
+    fn small_level() -> Level {
+        Level::parse("key R brick red 1 1 0\nboard\nR.R\n...\n").unwrap()
+    }
+
+    #[test]
+    fn check_cell_at_matches_world() {
+        let editor = Editor::new(Level::default_level());
+        let world = World::new(1.5, 0);
+
+        // The middle of every brick maps back to its own cell
+        for (i, brick) in world.bricks.iter().enumerate() {
+            let center = brick.quad.pos + 0.5 * brick.quad.dims;
+            let (x, y) = editor.cell_at(world.bounds, center).unwrap();
+            assert_eq!(y * editor.level.columns + x, i);
+            assert_eq!(editor.cell_pos(world.bounds, x, y), brick.quad.pos);
+        }
+
+        // Nothing past the edges
+        assert_eq!(editor.cell_at(world.bounds, Vec2::new(0.5, 10.)), None);
+        assert_eq!(
+            editor.cell_at(world.bounds, Vec2::new(10., world.bounds.y + 1.)),
+            None
+        );
+        assert_eq!(editor.cell_at(world.bounds, Vec2::new(10., 1.)), None);
+    }
+
+    #[test]
+    fn check_undo_and_redo() {
+        let mut editor = Editor::new(small_level());
+        let original = editor.level.clone();
+        let wall = Cell::Wall(crate::UNBREAKABLE_BRICK_COLOR);
+        editor.selected = editor.palette.iter().position(|c| *c == wall).unwrap();
+
+        // One click, then a drag across the bottom row
+        editor.paint(1, 0);
+        editor.begin_stroke();
+        for x in 0..3 {
+            editor.paint(x, 1);
+        }
+        editor.end_stroke();
+        editor.erase(0, 0);
+
+        assert_eq!(editor.level.cell(1, 0), wall);
+        assert_eq!(editor.level.cell(0, 0), Cell::Empty);
+        let edited = editor.level.clone();
+
+        // The drag comes back all at once
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert_eq!(editor.level.cell(0, 1), Cell::Empty);
+        assert_eq!(editor.level.cell(2, 1), Cell::Empty);
+        assert_eq!(editor.level.cell(1, 0), wall);
+
+        assert!(editor.undo());
+        assert_eq!(editor.level, original);
+        assert!(!editor.undo());
+
+        while editor.redo() {}
+        assert_eq!(editor.level, edited);
+
+        // A new edit throws away what we could have redone
+        editor.undo();
+        editor.erase(2, 0);
+        assert!(!editor.redo());
+    }
+
+    #[test]
+    fn check_painting_the_same_cell_does_nothing() {
+        let mut editor = Editor::new(small_level());
+        let red = editor.level.cell(0, 0);
+        assert!(!editor.set(0, 0, red));
+        assert!(!editor.undo());
+    }
+}
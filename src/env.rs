@@ -0,0 +1,398 @@
+//! A game wrapped up for training paddle agents, with no window, GPU or SDL involved.
+//!
+//! It's the usual reinforcement learning loop: [`Env::reset()`] starts an episode and [`Env::step()`] plays
+//! an [`Action`], giving back what the agent sees next, its reward, and whether the episode is over.
+//!
+//! ```
+//! use breakout::env::{Action, Env, EnvConfig};
+//!
+//! let mut env = Env::new(EnvConfig::default());
+//! let mut observation = env.reset(7);
+//! for _ in 0..1_000 {
+//!     // Serve, then follow the closest ball
+//!     let (paddle_x, ball_x, held) = (observation[0], observation[3], observation[7]);
+//!     let action = match ball_x - paddle_x {
+//!         _ if held == 1. => Action::Launch,
+//!         d if d < -0.02 => Action::Left,
+//!         d if d > 0.02 => Action::Right,
+//!         _ => Action::Stay,
+//!     };
+//!
+//!     let (next, _reward, done) = env.step(action);
+//!     if done {
+//!         break;
+//!     }
+//!     observation = next;
+//! }
+//! ```
+//!
+//! Observations are always the same length for an environment, see [`Env::observation_len()`]. They're
+//! either [`features()`] or a [`render()`]ed frame, picked with [`EnvConfig::observation`].
+
+use ultraviolet::Vec2;
+
+use std::sync::Arc;
+
+use crate::game::{Game, GameState};
+use crate::level::Level;
+use crate::timestep::DEFAULT_TICK_RATE;
+use crate::world::{FrameInput, Quad, World};
+
+/// What an agent sees after each step. Every value is somewhere between -2 and 2.
+pub type Observation = Vec<f32>;
+
+/// Everything the agent can do in a step
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Action {
+    #[default]
+    Stay,
+    Left,
+    Right,
+
+    /// Serve the ball, or let go of a caught one
+    Launch,
+}
+
+impl Action {
+    /// Every action, in the order agents with a numbered output usually want them
+    pub const ALL: [Action; 4] = [Action::Stay, Action::Left, Action::Right, Action::Launch];
+
+    pub fn input(self) -> FrameInput {
+        FrameInput {
+            left: self == Action::Left,
+            right: self == Action::Right,
+            launch: self == Action::Launch,
+            ..Default::default()
+        }
+    }
+}
+
+/// What observations look like
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObservationKind {
+    /// [`features()`], [`FEATURE_LEN`] of them
+    Features,
+
+    /// A grayscale [`render()`] of the playfield, `width * height` pixels
+    Frame { width: usize, height: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvConfig {
+    /// Every episode is played on this board
+    pub layout: Arc<Level>,
+    pub aspect: f32,
+    pub tick_rate: f32,
+
+    /// Game ticks each action is held for
+    pub ticks_per_step: u32,
+
+    /// Episodes end after this many steps, even if the game isn't over
+    pub max_steps: u32,
+
+    pub observation: ObservationKind,
+
+    /// Reward for each point scored
+    pub point_reward: f32,
+
+    /// Reward for each life lost. This is normally negative.
+    pub life_reward: f32,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            layout: Arc::new(Level::default_level()),
+            aspect: 1.5,
+            tick_rate: DEFAULT_TICK_RATE,
+            ticks_per_step: 4,
+            max_steps: 20_000,
+            observation: ObservationKind::Features,
+            point_reward: 0.01,
+            life_reward: -1.,
+        }
+    }
+}
+
+/// One player's game, stepped an [`Action`] at a time. Episodes end when the level is cleared, the game is
+/// over, or `max_steps` runs out.
+#[derive(Clone)]
+pub struct Env {
+    pub config: EnvConfig,
+    pub game: Game,
+
+    /// Steps since the last reset
+    pub steps: u32,
+
+    /// Scratch space to step into
+    next: Game,
+}
+
+impl Env {
+    /// Call [`Env::reset()`] before stepping
+    pub fn new(config: EnvConfig) -> Self {
+        let game = Game::from_level(config.layout.clone(), config.aspect, 0);
+
+        Self {
+            config,
+            game,
+            steps: 0,
+            next: Game::default(),
+        }
+    }
+
+    /// How long every observation is
+    pub fn observation_len(&self) -> usize {
+        match self.config.observation {
+            ObservationKind::Features => FEATURE_LEN,
+            ObservationKind::Frame { width, height } => width * height,
+        }
+    }
+
+    /// Start a new episode. The same seed and actions always play out the same way.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::from_level(self.config.layout.clone(), self.config.aspect, seed);
+        self.steps = 0;
+        self.observe()
+    }
+
+    /// Play `action` for `ticks_per_step` ticks. Returns what the agent sees now, the reward for this step,
+    /// and whether the episode is over. Once it's over, nothing happens until the next reset.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        if self.done() {
+            return (self.observe(), 0., true);
+        }
+
+        let before = self.game.players[0];
+        let input = action.input();
+        let dt = 1. / self.config.tick_rate;
+        for _ in 0..self.config.ticks_per_step {
+            self.game.step_into(&input, dt, &mut self.next);
+            std::mem::swap(&mut self.game, &mut self.next);
+            if self.game.state != GameState::Playing && self.game.state != GameState::Serve {
+                break;
+            }
+        }
+        self.steps += 1;
+
+        let after = self.game.players[0];
+        let points = (after.score.points - before.score.points) as f32;
+        let lives_lost = before.lives.saturating_sub(after.lives) as f32;
+        let reward = points * self.config.point_reward + lives_lost * self.config.life_reward;
+
+        (self.observe(), reward, self.done())
+    }
+
+    pub fn done(&self) -> bool {
+        matches!(
+            self.game.state,
+            GameState::LevelCleared | GameState::GameOver
+        ) || self.steps >= self.config.max_steps
+    }
+
+    /// What the agent sees right now
+    pub fn observe(&self) -> Observation {
+        match self.config.observation {
+            ObservationKind::Features => features(&self.game.world, 0),
+            ObservationKind::Frame { width, height } => render(&self.game.world, width, height),
+        }
+    }
+}
+
+/// Balls closest to the paddle that make it into [`features()`]
+pub const NEAREST_BALLS: usize = 3;
+
+/// Numbers for each of the [`NEAREST_BALLS`]
+const BALL_FEATURES: usize = 6;
+
+/// How finely [`features()`] splits up the playfield to say where the bricks are
+pub const OCCUPANCY_COLUMNS: usize = 16;
+pub const OCCUPANCY_ROWS: usize = 16;
+
+/// How many numbers [`features()`] always gives back
+pub const FEATURE_LEN: usize =
+    2 + NEAREST_BALLS * BALL_FEATURES + OCCUPANCY_COLUMNS * OCCUPANCY_ROWS;
+
+/// The world as a list of numbers, from where `paddle` is playing:
+///
+/// - The middle of the paddle and its width, as fractions of the playfield's width
+/// - For each of the [`NEAREST_BALLS`], closest first: 1 if it's there, its position as fractions of the
+///   playfield, its velocity in multiples of the starting ball speed, and 1 if it's held on a paddle.
+///   Missing balls are all zeros.
+/// - A [`OCCUPANCY_COLUMNS`] by [`OCCUPANCY_ROWS`] grid over the playfield, top row first, with 1 wherever
+///   there's the middle of a breakable brick
+pub fn features(world: &World, paddle: usize) -> Observation {
+    let mut features = Vec::with_capacity(FEATURE_LEN);
+    let bounds = world.bounds;
+
+    let quad = &world.paddles[paddle];
+    let center = quad.pos + 0.5 * quad.dims;
+    features.extend([center.x / bounds.x, quad.dims.x / bounds.x]);
+
+    let mut balls: Vec<_> = world.balls.iter().collect();
+    balls.sort_by(|a, b| {
+        (a.pos - center)
+            .mag_sq()
+            .total_cmp(&(b.pos - center).mag_sq())
+    });
+    let speed = world.rules.ball_speed;
+    for i in 0..NEAREST_BALLS {
+        match balls.get(i) {
+            Some(ball) => features.extend([
+                1.,
+                ball.pos.x / bounds.x,
+                ball.pos.y / bounds.y,
+                ball.vel.x / speed,
+                ball.vel.y / speed,
+                if ball.stuck.is_some() { 1. } else { 0. },
+            ]),
+            None => features.extend([0.; BALL_FEATURES]),
+        }
+    }
+
+    let mut grid = [0.; OCCUPANCY_COLUMNS * OCCUPANCY_ROWS];
+    for brick in &world.bricks {
+        let middle = brick.quad.pos + 0.5 * brick.quad.dims;
+        let x = (middle.x / bounds.x * OCCUPANCY_COLUMNS as f32) as usize;
+        let y = ((1. - middle.y / bounds.y) * OCCUPANCY_ROWS as f32) as usize;
+        if x < OCCUPANCY_COLUMNS && y < OCCUPANCY_ROWS {
+            grid[y * OCCUPANCY_COLUMNS + x] = 1.;
+        }
+    }
+    features.extend(grid);
+
+    features
+}
+
+/// Draw the world into a `width` by `height` grayscale frame, top row first, from 0 (nothing) to 1.
+///
+/// Walls are 0.25, bricks 0.5, capsules 0.75, and paddles, balls and lasers are 1. Anything that touches a
+/// pixel fills it, so small things like balls don't disappear at low resolutions.
+pub fn render(world: &World, width: usize, height: usize) -> Observation {
+    let mut frame = vec![0.; width * height];
+    let bounds = world.bounds;
+
+    let mut fill = |pos: Vec2, dims: Vec2, shade: f32| {
+        let left = (pos.x / bounds.x * width as f32).floor().max(0.) as usize;
+        let right = ((pos.x + dims.x) / bounds.x * width as f32).ceil() as usize;
+        let top = ((1. - (pos.y + dims.y) / bounds.y) * height as f32)
+            .floor()
+            .max(0.) as usize;
+        let bottom = ((1. - pos.y / bounds.y) * height as f32).ceil() as usize;
+
+        for y in top..bottom.min(height) {
+            for x in left..right.min(width) {
+                frame[y * width + x] = shade;
+            }
+        }
+    };
+    let mut fill_quad = |quad: &Quad, shade: f32| fill(quad.pos, quad.dims, shade);
+
+    for quad in &world.unbreakable_bricks {
+        fill_quad(quad, 0.25);
+    }
+    for brick in &world.bricks {
+        fill_quad(&brick.quad, 0.5);
+    }
+    for capsule in &world.capsules {
+        fill_quad(&capsule.quad, 0.75);
+    }
+    for laser in &world.lasers {
+        fill_quad(&laser.quad, 1.);
+    }
+    for quad in &world.paddles {
+        fill_quad(quad, 1.);
+    }
+    for ball in &world.balls {
+        let radius = Vec2::broadcast(ball.radius);
+        fill(ball.pos - radius, 2. * radius, 1.);
+    }
+
+    frame
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::controller::{Ai, PaddleController, Skill};
+    use pretty_assertions::assert_eq;
+
+    /// The action closest to what `input` does
+    fn action(input: FrameInput) -> Action {
+        match input {
+            FrameInput { launch: true, .. } => Action::Launch,
+            FrameInput { left: true, .. } => Action::Left,
+            FrameInput { right: true, .. } => Action::Right,
+            _ => Action::Stay,
+        }
+    }
+
+    #[test]
+    fn check_same_seed_same_episode() {
+        let play = |seed| {
+            let mut env = Env::new(EnvConfig::default());
+            let mut ai = Ai::new(Skill::PERFECT, DEFAULT_TICK_RATE, 0);
+            let mut observations = vec![env.reset(seed)];
+            let mut rewards = vec![];
+
+            for _ in 0..2_000 {
+                let (observation, reward, done) =
+                    env.step(action(ai.next_input(&env.game.world, 0)));
+                assert_eq!(observation.len(), env.observation_len());
+                observations.push(observation);
+                rewards.push(reward);
+                if done {
+                    break;
+                }
+            }
+            (observations, rewards)
+        };
+
+        let (observations, rewards) = play(5);
+        assert!(play(5) == (observations.clone(), rewards.clone()));
+        assert!(rewards.iter().sum::<f32>() > 0., "Nothing got broken");
+        assert!(observations.iter().all(|o| o.len() == FEATURE_LEN));
+    }
+
+    #[test]
+    fn check_episodes_end() {
+        let mut env = Env::new(EnvConfig {
+            max_steps: 50,
+            ..Default::default()
+        });
+        env.reset(0);
+
+        // Never serving does nothing, until time runs out
+        for _ in 0..49 {
+            let (_, reward, done) = env.step(Action::Left);
+            assert_eq!((reward, done), (0., false));
+        }
+        assert_eq!(env.step(Action::Stay).2, true);
+        assert_eq!(env.step(Action::Launch).2, true);
+        assert_eq!(env.steps, 50);
+
+        env.reset(0);
+        assert_eq!(env.steps, 0);
+        assert!(!env.done());
+    }
+
+    #[test]
+    fn check_frame() {
+        let (width, height) = (40, 60);
+        let mut env = Env::new(EnvConfig {
+            observation: ObservationKind::Frame { width, height },
+            ..Default::default()
+        });
+        let frame = env.reset(0);
+        assert_eq!(frame.len(), width * height);
+
+        // The paddle is at the bottom in the middle, with a ball on it, and there are bricks up top
+        let paddle = &env.game.world.paddles[0];
+        let middle = paddle.pos + 0.5 * paddle.dims;
+        let x = (middle.x / env.game.world.bounds.x * width as f32) as usize;
+        let y = ((1. - middle.y / env.game.world.bounds.y) * height as f32) as usize;
+        assert_eq!(frame[y * width + x], 1.);
+        assert_eq!(frame[(height - 1) * width], 0.);
+        assert!(frame[..width * height / 2].contains(&0.5));
+    }
+}
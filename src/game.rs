@@ -0,0 +1,412 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+use crate::level::Level;
+use crate::score::Score;
+use crate::world::{Ball, Event, FrameInput, Mode, World};
+
+/// Where we are in a game. See [`Game::step_into()`] for how we move between these.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameState {
+    /// The ball is resting on the serving player's paddle, waiting to be launched
+    #[default]
+    Serve,
+
+    /// The ball is in play
+    Playing,
+
+    /// Every brick is gone. Launching starts the next level.
+    LevelCleared,
+
+    /// Someone is out of lives. Launching starts a new game.
+    GameOver,
+}
+
+/// Everything we keep track of for each player
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    /// Balls this player can still lose, including the one in play
+    pub lives: u32,
+
+    pub score: Score,
+}
+
+/// Lives and levels wrapped around a [`World`].
+///
+/// The world only knows how to move things around. This decides when a ball that got away costs a life,
+/// and whose life it was, when the level is over, and what happens next.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Game {
+    pub world: World,
+    pub state: GameState,
+
+    pub mode: Mode,
+
+    /// One for each of the world's paddles, in the same order
+    pub players: Vec<Player>,
+
+    /// Whose paddle the next ball is served from. That's whoever lost the last one.
+    pub serving: usize,
+
+    /// Starts at 1
+    pub level: u32,
+
+    /// The board every level starts from
+    pub layout: Arc<Level>,
+}
+
+/// How many lives a new game starts with
+pub const STARTING_LIVES: u32 = 3;
+
+impl Game {
+    /// Start a new game on the default level. See [`Game::from_level()`].
+    pub fn new(aspect: f32, seed: u64) -> Self {
+        Self::from_level(Arc::new(Level::default_level()), aspect, seed)
+    }
+
+    /// Start a new single player game on the first level, serving the first ball.
+    ///
+    /// `layout`, `aspect` and `seed` are passed along to [`World::from_level()`].
+    pub fn from_level(layout: Arc<Level>, aspect: f32, seed: u64) -> Self {
+        Self::from_level_with(layout, aspect, seed, Mode::Single)
+    }
+
+    /// Start a new game for everyone in `mode`. The first player serves first.
+    pub fn from_level_with(layout: Arc<Level>, aspect: f32, seed: u64, mode: Mode) -> Self {
+        let player = Player {
+            lives: STARTING_LIVES,
+            score: Score::default(),
+        };
+
+        let mut game = Game {
+            world: World::from_level_with(&layout, aspect, seed, mode),
+            state: GameState::Serve,
+            mode,
+            players: vec![player; mode.players()],
+            serving: 0,
+            level: 1,
+            layout,
+        };
+        game.serve();
+
+        game
+    }
+
+    /// Advance the game by `dt` seconds and return the new game
+    pub fn step(&self, input: &FrameInput, dt: f32) -> Game {
+        let mut next = Game::default();
+        self.step_into(input, dt, &mut next);
+
+        next
+    }
+
+    /// Advance the game by `dt` seconds, with `input` for the first player and nothing from anyone else
+    pub fn step_into(&self, input: &FrameInput, dt: f32, next: &mut Game) {
+        self.step_players_into(std::slice::from_ref(input), dt, next);
+    }
+
+    /// Advance the game by `dt` seconds, writing the new game into `next`. `inputs` has one input for each
+    /// player.
+    ///
+    /// Like [`World::step_into()`], anything already in `next` is thrown away but its allocations are reused.
+    ///
+    /// - `Serve` goes to `Playing` when the serving player launches.
+    /// - `Playing` goes to `LevelCleared` when the last brick breaks, or costs whoever lost the last ball a
+    ///   life, and goes back to `Serve` with them serving (or `GameOver` when that was their last life).
+    /// - `LevelCleared` and `GameOver` wait for anyone to launch to start the next level or a new game.
+    pub fn step_players_into(&self, inputs: &[FrameInput], dt: f32, next: &mut Game) {
+        next.state = self.state;
+        next.mode = self.mode;
+        next.players.clone_from(&self.players);
+        next.serving = self.serving;
+        next.level = self.level;
+        next.layout.clone_from(&self.layout);
+
+        let launched = |player: usize| inputs.get(player).is_some_and(|input| input.launch);
+        match self.state {
+            GameState::LevelCleared | GameState::GameOver
+                if inputs.iter().any(|input| input.launch) =>
+            {
+                // Don't pass the launch along, or we'd launch the new serve right away
+                let inputs: Vec<_> = inputs
+                    .iter()
+                    .map(|input| FrameInput {
+                        launch: false,
+                        ..*input
+                    })
+                    .collect();
+                self.world.step_players_into(&inputs, dt, &mut next.world);
+
+                if self.state == GameState::LevelCleared {
+                    next.next_level();
+                } else {
+                    next.new_game();
+                }
+
+                return;
+            }
+            GameState::Serve if launched(self.serving) => next.state = GameState::Playing,
+            _ => {}
+        }
+
+        self.world.step_players_into(inputs, dt, &mut next.world);
+
+        // Everyone only scores what they did themselves
+        let world = &next.world;
+        for (i, player) in next.players.iter_mut().enumerate() {
+            let theirs = world.events.iter();
+            player
+                .score
+                .record(theirs.filter(|event| event_player(world, event) == Some(i)));
+            if next.state == GameState::Playing {
+                player.score.level_time += dt;
+            }
+        }
+
+        match next.state {
+            GameState::Serve if next.world.balls.is_empty() => {
+                // Someone cleared the ball we were serving, so give them another one
+                next.serve();
+            }
+            GameState::Playing if next.world.bricks.is_empty() => {
+                next.state = GameState::LevelCleared;
+                for player in &mut next.players {
+                    player.score.level_cleared();
+                }
+                next.clear_playfield();
+            }
+            GameState::Playing if next.world.balls.is_empty() => {
+                // Balls can also go away by being cleared, and then it's on whoever was serving
+                let lost_by = next
+                    .world
+                    .events
+                    .iter()
+                    .rev()
+                    .find_map(|event| match event {
+                        Event::BallLost { ball } => Some(losing_player(&next.world, ball)),
+                        _ => None,
+                    });
+                next.serving = lost_by.unwrap_or(next.serving);
+
+                let player = &mut next.players[next.serving];
+                player.lives = player.lives.saturating_sub(1);
+                if player.lives == 0 {
+                    next.state = GameState::GameOver;
+                    next.clear_playfield();
+                } else {
+                    next.state = GameState::Serve;
+                    next.serve();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Take away every ball, and put a new one on the serving player's paddle
+    fn serve(&mut self) {
+        self.world.balls.clear();
+        self.world.serve_ball(self.serving);
+        self.state = GameState::Serve;
+    }
+
+    /// Take away everything that's still moving, so nothing happens while we wait between levels or games
+    fn clear_playfield(&mut self) {
+        self.world.balls.clear();
+        self.world.capsules.clear();
+        self.world.lasers.clear();
+        self.world.effects = Default::default();
+    }
+
+    /// A fresh board with the same lives
+    fn next_level(&mut self) {
+        let aspect = self.world.bounds.y / self.world.bounds.x;
+        let seed = self.world.rng.next_u64();
+
+        self.world = World::from_level_with(&self.layout, aspect, seed, self.mode);
+        self.level += 1;
+        self.serve();
+    }
+
+    /// Start over from the first level
+    fn new_game(&mut self) {
+        let aspect = self.world.bounds.y / self.world.bounds.x;
+        let seed = self.world.rng.next_u64();
+
+        *self = Game::from_level_with(self.layout.clone(), aspect, seed, self.mode);
+    }
+}
+
+/// Which player an event counts for, if it's anyone's
+fn event_player(world: &World, event: &Event) -> Option<usize> {
+    match event {
+        Event::BrickDamaged { player, .. } | Event::BrickBroken { player, .. } => Some(*player),
+        Event::PaddleHit { ball } => Some(ball.player),
+        Event::BallLost { ball } => Some(losing_player(world, ball)),
+        Event::PowerUpCollected { .. } => None,
+    }
+}
+
+/// Whoever was guarding the side of the playfield that `ball` got out of. When players share a side, it's
+/// the one who touched it last.
+fn losing_player(world: &World, ball: &Ball) -> usize {
+    let side = if ball.pos.y > 0.5 * world.bounds.y {
+        -1.
+    } else {
+        1.
+    };
+    let guarding = |player: &usize| world.paddle_facing(*player) == side;
+
+    if ball.player < world.paddles.len() && guarding(&ball.player) {
+        ball.player
+    } else {
+        (0..world.paddles.len()).find(guarding).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use ultraviolet::Vec2;
+
+    const DT: f32 = 5e-3;
+    const SEED: u64 = 0xb0a7;
+
+    const LAUNCH: FrameInput = FrameInput {
+        left: false,
+        right: false,
+        spawn_ball: false,
+        clear_balls: false,
+        launch: true,
+    };
+
+    const IDLE: FrameInput = FrameInput {
+        launch: false,
+        ..LAUNCH
+    };
+
+    #[test]
+    fn check_serve_waits_for_launch() {
+        let mut game = Game::new(1.5, SEED);
+        assert_eq!(game.state, GameState::Serve);
+        assert_eq!(game.world.balls.len(), 1);
+
+        // The ball stays put on the paddle
+        let ball = game.world.balls[0];
+        for _ in 0..100 {
+            game = game.step(&IDLE, DT);
+        }
+        assert_eq!(game.state, GameState::Serve);
+        assert_eq!(game.world.balls[0].pos, ball.pos);
+
+        let game = game.step(&LAUNCH, DT).step(&IDLE, DT);
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.world.balls[0].stuck, None);
+        assert!(game.world.balls[0].pos.y > ball.pos.y);
+    }
+
+    #[test]
+    fn check_losing_balls_costs_lives() {
+        let mut game = Game::new(1.5, SEED);
+
+        for lives_left in (0..STARTING_LIVES).rev() {
+            game = game.step(&LAUNCH, DT);
+            assert_eq!(game.state, GameState::Playing);
+
+            // Throw the ball out the bottom
+            game.world.balls[0].pos = Vec2::new(0.5 * game.world.bounds.x, 0.1);
+            game.world.balls[0].vel = Vec2::new(0., -100.);
+            game = game.step(&IDLE, DT).step(&IDLE, DT);
+
+            assert_eq!(game.players[0].lives, lives_left);
+            if lives_left > 0 {
+                assert_eq!(game.state, GameState::Serve);
+                assert_eq!(game.world.balls.len(), 1);
+            }
+        }
+
+        assert_eq!(game.state, GameState::GameOver);
+        assert!(game.world.balls.is_empty());
+
+        // Launching again starts over
+        let game = game.step(&LAUNCH, DT);
+        assert_eq!(game.state, GameState::Serve);
+        assert_eq!(game.players[0].lives, STARTING_LIVES);
+        assert_eq!(game.level, 1);
+    }
+
+    #[test]
+    fn check_clearing_the_level() {
+        let mut game = Game::new(1.5, SEED).step(&LAUNCH, DT);
+
+        // Leave one brick, right in the ball's way
+        let ball = game.world.balls[0];
+        let mut brick = game.world.bricks[0];
+        brick.hp = 1;
+        brick.quad.pos = ball.pos + Vec2::new(-0.5 * brick.quad.dims.x, 1.);
+        game.world.bricks = vec![brick];
+        game.world.balls[0].vel = Vec2::new(0., 100.);
+
+        let game = game.step(&IDLE, DT).step(&IDLE, DT);
+        assert_eq!(game.state, GameState::LevelCleared);
+        assert!(game.world.balls.is_empty());
+
+        // That was quick, so there's a bonus on top of the brick
+        assert!(game.players[0].score.last_bonus > 0);
+        assert_eq!(
+            game.players[0].score.points,
+            brick.points as u64 + game.players[0].score.last_bonus
+        );
+
+        // Nothing happens until the player moves on
+        let game = game.step(&IDLE, DT);
+        assert_eq!(game.state, GameState::LevelCleared);
+
+        let game = game.step(&LAUNCH, DT);
+        assert_eq!(game.state, GameState::Serve);
+        assert_eq!(game.level, 2);
+        assert_eq!(game.players[0].lives, STARTING_LIVES);
+        assert!(!game.world.bricks.is_empty());
+    }
+
+    #[test]
+    fn check_versus_lives_and_scores_are_separate() {
+        let layout = Arc::new(Level::default_level());
+        let mut game = Game::from_level_with(layout, 1.5, SEED, Mode::Versus);
+        assert_eq!(game.players.len(), 2);
+        assert_eq!(game.world.balls[0].player, 0);
+
+        // Only the serving player can launch
+        let mut next = Game::default();
+        game.step_players_into(&[IDLE, LAUNCH], DT, &mut next);
+        assert_eq!(next.state, GameState::Serve);
+        game.step_players_into(&[LAUNCH, IDLE], DT, &mut next);
+        game = next.clone();
+        assert_eq!(game.state, GameState::Playing);
+
+        // The first player breaks a brick, and gets the points for it
+        let ball = game.world.balls[0];
+        let mut brick = game.world.bricks[0];
+        brick.hp = 1;
+        brick.quad.pos = ball.pos + Vec2::new(-0.5 * brick.quad.dims.x, 1.);
+        game.world.bricks[0] = brick;
+        game.world.rebuild_brick_grid();
+        game.world.balls[0].vel = Vec2::new(0., 100.);
+        let mut game = game.step(&IDLE, DT).step(&IDLE, DT);
+        assert_eq!(game.players[0].score.points, brick.points as u64);
+        assert_eq!(game.players[1].score.points, 0);
+
+        // Then it gets past the second player at the top, which costs them a life and they serve next
+        let top = game.world.bounds.y;
+        game.world.balls[0].pos = Vec2::new(1., top - 0.1);
+        game.world.balls[0].vel = Vec2::new(0., 100.);
+        let game = game.step(&IDLE, DT).step(&IDLE, DT);
+        assert_eq!(game.state, GameState::Serve);
+        assert_eq!(game.players[0].lives, STARTING_LIVES);
+        assert_eq!(game.players[1].lives, STARTING_LIVES - 1);
+        assert_eq!(game.serving, 1);
+        assert_eq!(game.world.balls[0].stuck.unwrap().paddle, 1);
+    }
+}
@@ -0,0 +1,338 @@
+//! Boards made up from a seed, for when we've played all the ones we have.
+//!
+//! ```
+//! use breakout::generate::{generate, Params, Pattern};
+//!
+//! let params = Params {
+//!     pattern: Some(Pattern::Diamond),
+//!     ..Default::default()
+//! };
+//! let levels: Vec<_> = (0..10).map(|seed| generate(seed, &params)).collect();
+//! ```
+
+use rand::prelude::*;
+
+use crate::level::{BrickType, Cell, Level};
+use crate::rng::Pcg32;
+
+/// The overall shape of a board, before `density` knocks bricks out of it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Bricks anywhere
+    Scatter,
+
+    /// Narrow at the top and wide at the bottom
+    Pyramid,
+
+    /// Every other cell, alternating each row. Mirroring this puts two bricks side by side in the middle
+    /// unless there's an odd number of columns.
+    Checkerboard,
+
+    /// Widest in the middle, and pointed at the top and bottom
+    Diamond,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 4] = [
+        Pattern::Scatter,
+        Pattern::Pyramid,
+        Pattern::Checkerboard,
+        Pattern::Diamond,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "scatter" => Some(Pattern::Scatter),
+            "pyramid" => Some(Pattern::Pyramid),
+            "checkerboard" => Some(Pattern::Checkerboard),
+            "diamond" => Some(Pattern::Diamond),
+            _ => None,
+        }
+    }
+
+    /// Whether the pattern has a brick at `(x, y)` on a `columns` by `rows` board
+    fn contains(self, x: usize, y: usize, columns: usize, rows: usize) -> bool {
+        // How far the middle of the cell is from the middle of the board, from 0 to 1
+        let u = ((x as f32 + 0.5) / columns as f32 - 0.5).abs() * 2.;
+        let v = (y as f32 + 0.5) / rows as f32;
+
+        match self {
+            Pattern::Scatter => true,
+            Pattern::Pyramid => u <= v,
+            Pattern::Checkerboard => (x + y).is_multiple_of(2),
+            Pattern::Diamond => u + (2. * v - 1.).abs() <= 1.,
+        }
+    }
+}
+
+/// Which parts of a board are copies of each other
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Every cell is rolled on its own
+    None,
+
+    /// The right half mirrors the left
+    #[default]
+    Mirror,
+
+    /// The right half mirrors the left, and the bottom half mirrors the top
+    Quad,
+}
+
+impl Symmetry {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Symmetry::None),
+            "mirror" => Some(Symmetry::Mirror),
+            "quad" => Some(Symmetry::Quad),
+            _ => None,
+        }
+    }
+}
+
+/// What kind of board to make. Everything else comes from the seed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Params {
+    pub columns: usize,
+    pub rows: usize,
+
+    /// Chance from 0 to 1 that a cell in the pattern gets a brick
+    pub density: f32,
+
+    pub symmetry: Symmetry,
+
+    /// Picked from the seed when this is `None`
+    pub pattern: Option<Pattern>,
+
+    /// Chance from 0 to 1 that a brick is an unbreakable wall
+    pub wall_fraction: f32,
+
+    /// Chance from 0 to 1 that a breakable brick takes more than one hit
+    pub multi_hit_fraction: f32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            columns: 14,
+            rows: 30,
+            density: 0.8,
+            symmetry: Symmetry::Mirror,
+            pattern: None,
+            wall_fraction: 0.05,
+            multi_hit_fraction: 0.25,
+        }
+    }
+}
+
+/// Make a board from `seed`. The same seed and `params` always make the same board.
+///
+/// Bricks are the same kinds the default level has, with tougher ones towards the top. Everything but the
+/// board itself (brick and paddle size, ball speed) comes from the default level too. There's always at
+/// least one brick to break, so a board is never over before it starts.
+pub fn generate(seed: u64, params: &Params) -> Level {
+    let mut rng = Pcg32::new(seed);
+    let (columns, rows) = (params.columns.max(1), params.rows.max(1));
+    let pattern = params
+        .pattern
+        .unwrap_or_else(|| *Pattern::ALL.choose(&mut rng).unwrap());
+
+    let default_level = Level::default_level();
+    let mut single_hit = vec![];
+    let mut multi_hit = vec![];
+    for cell in &default_level.cells {
+        if let Cell::Brick(brick) = cell {
+            let kinds = if brick.hp > 1 {
+                &mut multi_hit
+            } else {
+                &mut single_hit
+            };
+            if !kinds.contains(brick) {
+                kinds.push(*brick);
+            }
+        }
+    }
+    // Toughest first, so the top of the board gets the tough ones
+    multi_hit.sort_by_key(|brick: &BrickType| std::cmp::Reverse(brick.hp));
+
+    let mut level = Level {
+        columns,
+        rows,
+        cells: vec![Cell::Empty; columns * rows],
+        ..default_level
+    };
+
+    // Roll the top left of the board, and copy it to the rest
+    let rolled_columns = match params.symmetry {
+        Symmetry::None => columns,
+        Symmetry::Mirror | Symmetry::Quad => columns.div_ceil(2),
+    };
+    let rolled_rows = match params.symmetry {
+        Symmetry::None | Symmetry::Mirror => rows,
+        Symmetry::Quad => rows.div_ceil(2),
+    };
+
+    for y in 0..rolled_rows {
+        for x in 0..rolled_columns {
+            if !pattern.contains(x, y, columns, rows) || rng.random::<f32>() >= params.density {
+                continue;
+            }
+
+            // Pick from each list by how far down the board we are
+            let depth = y as f32 / rows as f32;
+            let pick = |kinds: &[BrickType]| kinds[(depth * kinds.len() as f32) as usize];
+
+            let cell = if rng.random::<f32>() < params.wall_fraction {
+                Cell::Wall(crate::UNBREAKABLE_BRICK_COLOR)
+            } else if rng.random::<f32>() < params.multi_hit_fraction {
+                Cell::Brick(pick(&multi_hit))
+            } else {
+                Cell::Brick(pick(&single_hit))
+            };
+
+            let copies = match params.symmetry {
+                Symmetry::None => 1,
+                Symmetry::Mirror => 2,
+                Symmetry::Quad => 4,
+            };
+            let (mirror_x, mirror_y) = (columns - 1 - x, rows - 1 - y);
+            for (x, y) in [(x, y), (mirror_x, y), (x, mirror_y), (mirror_x, mirror_y)]
+                .into_iter()
+                .take(copies)
+            {
+                level.cells[y * columns + x] = cell;
+            }
+        }
+    }
+
+    if !level
+        .cells
+        .iter()
+        .any(|cell| matches!(cell, Cell::Brick(_)))
+    {
+        level.cells[columns / 2] = Cell::Brick(single_hit[0]);
+    }
+
+    level
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::world::World;
+    use pretty_assertions::assert_eq;
+
+    fn bricks(level: &Level) -> usize {
+        let is_brick = |cell: &&Cell| matches!(cell, Cell::Brick(_));
+        level.cells.iter().filter(is_brick).count()
+    }
+
+    #[test]
+    fn check_same_seed_same_board() {
+        let params = Params::default();
+        assert_eq!(generate(7, &params), generate(7, &params));
+        assert_ne!(generate(7, &params), generate(8, &params));
+
+        // And the world can play it
+        let level = generate(7, &params);
+        let world = World::from_level(&level, 1.5, 0);
+        assert_eq!(world.bricks.len(), bricks(&level));
+    }
+
+    #[test]
+    fn check_symmetry() {
+        for seed in 0..20 {
+            let params = Params {
+                columns: 13,
+                symmetry: Symmetry::Quad,
+                ..Default::default()
+            };
+            let level = generate(seed, &params);
+            let (columns, rows) = (level.columns, level.rows);
+
+            for y in 0..rows {
+                for x in 0..columns {
+                    let cell = level.cell(x, y);
+                    assert_eq!(cell, level.cell(columns - 1 - x, y), "({x}, {y})");
+                    assert_eq!(cell, level.cell(x, rows - 1 - y), "({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_patterns() {
+        let full = |pattern| Params {
+            density: 1.,
+            pattern: Some(pattern),
+            wall_fraction: 0.,
+            ..Default::default()
+        };
+
+        // No two bricks side by side
+        let checkerboard = Params {
+            symmetry: Symmetry::None,
+            ..full(Pattern::Checkerboard)
+        };
+        let level = generate(0, &checkerboard);
+        for y in 0..level.rows {
+            for x in 1..level.columns {
+                let pair = [level.cell(x - 1, y), level.cell(x, y)];
+                assert!(pair.contains(&Cell::Empty), "({x}, {y})");
+            }
+        }
+
+        // Every row of a pyramid is at least as wide as the one above it
+        let level = generate(0, &full(Pattern::Pyramid));
+        let widths: Vec<_> = (0..level.rows)
+            .map(|y| {
+                (0..level.columns)
+                    .filter(|&x| level.cell(x, y) != Cell::Empty)
+                    .count()
+            })
+            .collect();
+        assert!(widths.windows(2).all(|w| w[0] <= w[1]), "{widths:?}");
+        assert_eq!(widths.last(), Some(&level.columns));
+
+        // A diamond doesn't reach the corners
+        let level = generate(0, &full(Pattern::Diamond));
+        let (right, bottom) = (level.columns - 1, level.rows - 1);
+        for (x, y) in [(0, 0), (right, 0), (0, bottom), (right, bottom)] {
+            assert_eq!(level.cell(x, y), Cell::Empty);
+        }
+        assert_ne!(level.cell(level.columns / 2, level.rows / 2), Cell::Empty);
+    }
+
+    #[test]
+    fn check_fractions() {
+        let params = Params {
+            columns: 40,
+            rows: 40,
+            density: 0.5,
+            symmetry: Symmetry::None,
+            pattern: Some(Pattern::Scatter),
+            wall_fraction: 0.2,
+            multi_hit_fraction: 0.5,
+        };
+        let level = generate(0, &params);
+        let count = |f: fn(&Cell) -> bool| level.cells.iter().filter(|c| f(c)).count();
+
+        let filled = count(|c| *c != Cell::Empty);
+        let walls = count(|c| matches!(c, Cell::Wall(_)));
+        let multi_hit = count(|c| matches!(c, Cell::Brick(brick) if brick.hp > 1));
+
+        assert!((700..900).contains(&filled), "{filled} filled");
+        assert!((100..220).contains(&walls), "{walls} walls");
+        assert!((250..400).contains(&multi_hit), "{multi_hit} multi-hit");
+
+        // Even an empty board gets a brick
+        let empty = generate(
+            0,
+            &Params {
+                density: 0.,
+                ..params
+            },
+        );
+        assert_eq!(bricks(&empty), 1);
+    }
+}
@@ -332,6 +332,17 @@ impl Window {
             check_sdl_error("SDL_ShowWindow");
         }
     }
+
+    /// Size of the window in the same units as mouse events, which aren't always pixels
+    pub fn size(&self) -> (i32, i32) {
+        let mut width = 0;
+        let mut height = 0;
+        unsafe {
+            SDL_GetWindowSize(self.0.p_window, &mut width, &mut height);
+        }
+
+        (width, height)
+    }
 }
 
 impl Window {
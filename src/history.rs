@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+
+use crate::game::Game;
+
+/// The last few seconds of a game, for rewinding.
+///
+/// A game with a full board is pretty big, so this only keeps every `stride`th tick. Once it's full, the
+/// oldest game makes room for the newest one.
+#[derive(Clone, Default)]
+pub struct History {
+    /// Past games and the tick each one is from, oldest first
+    games: VecDeque<(u64, Game)>,
+
+    /// Most games we keep
+    capacity: usize,
+
+    pub stride: u64,
+}
+
+impl History {
+    /// Keep about `seconds` worth of ticks at `tick_rate`, one every `stride` ticks
+    pub fn new(seconds: f32, tick_rate: f32, stride: u64) -> Self {
+        let stride = stride.max(1);
+        let capacity = (seconds * tick_rate / stride as f32).ceil() as usize;
+
+        Self {
+            games: VecDeque::with_capacity(capacity),
+            capacity,
+            stride,
+        }
+    }
+
+    /// Remember `game` as it was after `tick` ticks, if that's one of the ticks we keep
+    pub fn record(&mut self, tick: u64, game: &Game) {
+        if !tick.is_multiple_of(self.stride) || self.capacity == 0 {
+            return;
+        }
+
+        if self.games.len() == self.capacity {
+            self.games.pop_front();
+        }
+        self.games.push_back((tick, game.clone()));
+    }
+
+    /// Take the latest game back out, with the tick it's from. Calling this over and over goes further back.
+    pub fn pop(&mut self) -> Option<(u64, Game)> {
+        self.games.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Forget everything, like when a different game starts
+    pub fn clear(&mut self) {
+        self.games.clear();
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::world::FrameInput;
+    use pretty_assertions::assert_eq;
+
+    const DT: f32 = 5e-3;
+
+    #[test]
+    fn check_history_is_bounded() {
+        let mut history = History::new(1., 100., 4);
+        let mut game = Game::new(1.5, 0);
+        let input = FrameInput {
+            launch: true,
+            ..Default::default()
+        };
+
+        for tick in 1..=1_000 {
+            game = game.step(&input, DT);
+            history.record(tick, &game);
+        }
+        assert_eq!(history.len(), 25);
+
+        // Newest first, every 4 ticks
+        let ticks: Vec<_> = std::iter::from_fn(|| history.pop().map(|(tick, _)| tick)).collect();
+        assert_eq!(ticks[..3], [1_000, 996, 992]);
+        assert_eq!(ticks.last(), Some(&904));
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn check_rewinding_and_playing_again() {
+        let mut history = History::new(1., 200., 1);
+        let launch = FrameInput {
+            launch: true,
+            right: true,
+            ..Default::default()
+        };
+
+        let mut game = Game::new(1.5, 0xb0a7);
+        let mut games = vec![game.clone()];
+        for tick in 1..=300 {
+            game = game.step(&launch, DT);
+            history.record(tick, &game);
+            games.push(game.clone());
+        }
+
+        // Back to where we were 50 ticks ago
+        let (mut tick, mut rewound) = history.pop().unwrap();
+        for _ in 0..50 {
+            (tick, rewound) = history.pop().unwrap();
+        }
+        assert_eq!(tick, 250);
+        assert!(rewound == games[250]);
+
+        // The same inputs from there get us back to the same place
+        for _ in 250..300 {
+            rewound = rewound.step(&launch, DT);
+        }
+        assert!(rewound == game, "Played out differently after rewinding");
+    }
+}
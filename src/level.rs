@@ -0,0 +1,470 @@
+//! Boards, loaded from plain text.
+//!
+//! A level file is a header of `<key> <values...>` lines, then a `board` line, then one line per row of
+//! bricks from the top down. Blank lines and lines starting with `#` are ignored in the header.
+//!
+//! ```text
+//! # Columns and rows. This is optional, but the board has to match it when it's here.
+//! size 3 2
+//! # Width and height of the bricks and the paddle
+//! brick 5 1
+//! paddle 20 1
+//! ball_speed 135
+//!
+//! # Char, color, hit points, points, and the chance to drop a power-up for a breakable brick
+//! key R brick red 3 7 0.3
+//! # Char and color for an unbreakable brick
+//! key X wall gray
+//!
+//! board
+//! RXR
+//! R.R
+//! ```
+//!
+//! `.` is always an empty spot. Colors are either one of `red`, `orange`, `green`, `yellow`, `pink`, `gray`
+//! and `white`, or `r,g,b` with each part from 0 to 1.
+
+use serde::{Deserialize, Serialize};
+use ultraviolet::{Vec2, Vec3};
+
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::color;
+use crate::powerup::DropTable;
+use crate::world::{Rules, BRICK_DIMS};
+
+/// The board we've always had, and what we play when nobody asks for anything else
+pub const DEFAULT_LEVEL: &str = include_str!("../levels/default.txt");
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub columns: usize,
+    pub rows: usize,
+
+    pub brick_dims: Vec2,
+    pub paddle_dims: Vec2,
+    pub ball_speed: f32,
+
+    /// `columns * rows` cells, one row at a time from the top
+    pub cells: Vec<Cell>,
+}
+
+/// One spot on the board
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Cell {
+    #[default]
+    Empty,
+    Brick(BrickType),
+
+    /// An unbreakable brick of this color
+    Wall(Vec3),
+}
+
+impl Cell {
+    /// What this looks like on the board, if there's anything there
+    pub fn color(&self) -> Option<Vec3> {
+        match self {
+            Cell::Empty => None,
+            Cell::Brick(brick) => Some(brick.color),
+            Cell::Wall(color) => Some(*color),
+        }
+    }
+}
+
+/// Everything about a breakable brick except where it goes
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BrickType {
+    pub color: Vec3,
+    pub hp: u32,
+    pub points: u32,
+    pub drops: DropTable,
+}
+
+impl Level {
+    /// The level from `DEFAULT_LEVEL`
+    pub fn default_level() -> Self {
+        Self::parse(DEFAULT_LEVEL).expect("The default level should always parse")
+    }
+
+    /// Read and parse a level file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut size: Option<(usize, usize)> = None;
+        let mut level = Level {
+            brick_dims: BRICK_DIMS,
+            paddle_dims: Vec2::new(4. * BRICK_DIMS.x, BRICK_DIMS.y),
+            ball_speed: Rules::default().ball_speed,
+            ..Default::default()
+        };
+        let mut keys: Vec<(char, Cell)> = vec![('.', Cell::Empty)];
+
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        // Header
+        loop {
+            let Some((n, line)) = lines.next() else {
+                return Err("Missing the `board` line".to_string());
+            };
+            let err = |e: String| format!("line {n}: {e}");
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let key = words.next().unwrap();
+            match key {
+                "board" => break,
+                "size" => {
+                    size = Some((
+                        parse(words.next(), "columns").map_err(err)?,
+                        parse(words.next(), "rows").map_err(err)?,
+                    ));
+                }
+                "brick" => level.brick_dims = parse_dims(&mut words).map_err(err)?,
+                "paddle" => level.paddle_dims = parse_dims(&mut words).map_err(err)?,
+                "ball_speed" => {
+                    level.ball_speed = parse(words.next(), "speed").map_err(err)?;
+                    if !level.ball_speed.is_finite() || level.ball_speed <= 0. {
+                        return Err(err(format!(
+                            "Ball speed must be positive, but it's {}",
+                            level.ball_speed
+                        )));
+                    }
+                }
+                "key" => {
+                    let (c, cell) = parse_key(&mut words).map_err(err)?;
+                    if keys.iter().any(|(other, _)| *other == c) {
+                        return Err(err(format!("'{c}' already means something else")));
+                    }
+                    keys.push((c, cell));
+                }
+                _ => return Err(err(format!("Unknown header line \"{key}\""))),
+            }
+
+            if let Some(extra) = words.next() {
+                return Err(err(format!(
+                    "Unexpected \"{extra}\" at the end of the line"
+                )));
+            }
+        }
+
+        // Board
+        let mut board: Vec<(usize, &str)> = lines.map(|(n, line)| (n, line.trim_end())).collect();
+        while board.last().is_some_and(|(_, line)| line.is_empty()) {
+            board.pop();
+        }
+
+        level.rows = board.len();
+        level.columns = board.first().map_or(0, |(_, line)| line.chars().count());
+        if let Some((columns, rows)) = size {
+            if (columns, rows) != (level.columns, level.rows) {
+                return Err(format!(
+                    "The header says the board is {columns}x{rows}, but it's {}x{}",
+                    level.columns, level.rows
+                ));
+            }
+        }
+
+        for (n, line) in board {
+            if line.chars().count() != level.columns {
+                return Err(format!(
+                    "line {n}: Every row should be {} wide, but this one is {}",
+                    level.columns,
+                    line.chars().count()
+                ));
+            }
+
+            for c in line.chars() {
+                let Some((_, cell)) = keys.iter().find(|(key, _)| *key == c) else {
+                    return Err(format!("line {n}: '{c}' doesn't have a `key` line"));
+                };
+                level.cells.push(*cell);
+            }
+        }
+
+        Ok(level)
+    }
+
+    /// What's at column `x` of row `y`, counting rows from the top
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[y * self.columns + x]
+    }
+
+    /// Write this level in the format that [`Level::parse()`] reads.
+    ///
+    /// Drop tables are saved as just their `chance`, so anything that isn't [`DropTable::even()`] loses its
+    /// weights.
+    pub fn to_text(&self) -> String {
+        use std::fmt::Write;
+
+        // Give every kind of cell a character, preferring the first letter of its color's name
+        let mut keys: Vec<(char, Cell)> = vec![('.', Cell::Empty)];
+        for cell in &self.cells {
+            if keys.iter().any(|(_, other)| other == cell) {
+                continue;
+            }
+
+            let preferred = match cell {
+                Cell::Wall(_) => Some('X'),
+                Cell::Brick(kind) => color_name(kind.color)
+                    .and_then(|name| name.chars().next())
+                    .map(|c| c.to_ascii_uppercase()),
+                Cell::Empty => None,
+            };
+            let c = preferred
+                .into_iter()
+                .chain(KEY_CHARS.chars())
+                .find(|c| keys.iter().all(|(other, _)| other != c))
+                .expect("Ran out of characters for the kinds of bricks in this level");
+            keys.push((c, *cell));
+        }
+
+        let mut text = String::new();
+        let dims = |v: Vec2| format!("{} {}", v.x, v.y);
+        writeln!(text, "size {} {}", self.columns, self.rows).unwrap();
+        writeln!(text, "brick {}", dims(self.brick_dims)).unwrap();
+        writeln!(text, "paddle {}", dims(self.paddle_dims)).unwrap();
+        writeln!(text, "ball_speed {}", self.ball_speed).unwrap();
+        writeln!(text).unwrap();
+
+        for (c, cell) in &keys {
+            match cell {
+                Cell::Empty => {}
+                Cell::Brick(kind) => writeln!(
+                    text,
+                    "key {c} brick {} {} {} {}",
+                    format_color(kind.color),
+                    kind.hp,
+                    kind.points,
+                    kind.drops.chance
+                )
+                .unwrap(),
+                Cell::Wall(color) => {
+                    writeln!(text, "key {c} wall {}", format_color(*color)).unwrap()
+                }
+            }
+        }
+        writeln!(text).unwrap();
+
+        writeln!(text, "board").unwrap();
+        for row in self.cells.chunks(self.columns.max(1)) {
+            for cell in row {
+                let (c, _) = keys.iter().find(|(_, other)| other == cell).unwrap();
+                text.push(*c);
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+/// Characters for `key` lines when the color's name is taken
+const KEY_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Colors that can be written by name instead of `r,g,b`
+const NAMED_COLORS: [(&str, Vec3); 7] = [
+    ("red", color::RED),
+    ("orange", color::ORANGE),
+    ("green", color::GREEN),
+    ("yellow", color::YELLOW),
+    ("pink", color::OHNO_PINK),
+    ("gray", color::GRAY),
+    ("white", color::WHITE),
+];
+
+fn color_name(color: Vec3) -> Option<&'static str> {
+    NAMED_COLORS
+        .iter()
+        .find(|(_, named)| *named == color)
+        .map(|(name, _)| *name)
+}
+
+fn format_color(color: Vec3) -> String {
+    match color_name(color) {
+        Some(name) => name.to_string(),
+        None => format!("{},{},{}", color.x, color.y, color.z),
+    }
+}
+
+fn parse<T>(word: Option<&str>, what: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let word = word.ok_or_else(|| format!("Missing {what}"))?;
+    word.parse()
+        .map_err(|e| format!("Bad {what} \"{word}\": {e}"))
+}
+
+fn parse_dims<'a>(words: &mut impl Iterator<Item = &'a str>) -> Result<Vec2, String> {
+    let dims = Vec2::new(
+        parse(words.next(), "width")?,
+        parse(words.next(), "height")?,
+    );
+    if !(dims.x.is_finite() && dims.y.is_finite() && dims.x > 0. && dims.y > 0.) {
+        return Err(format!("Sizes must be positive, but this is {dims:?}"));
+    }
+
+    Ok(dims)
+}
+
+fn parse_key<'a>(words: &mut impl Iterator<Item = &'a str>) -> Result<(char, Cell), String> {
+    let key = words.next().ok_or("Missing the character for this key")?;
+    let mut chars = key.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return Err(format!("Keys are a single character, not \"{key}\""));
+    };
+
+    let kind = words.next().ok_or("Missing the kind of brick")?;
+    let color = parse_color(words.next().ok_or("Missing the color")?)?;
+
+    let cell = match kind {
+        "brick" => {
+            let hp = parse(words.next(), "hit points")?;
+            if hp == 0 {
+                return Err("Bricks need at least one hit point".to_string());
+            }
+
+            Cell::Brick(BrickType {
+                color,
+                hp,
+                points: parse(words.next(), "points")?,
+                drops: DropTable::even(parse(words.next(), "drop chance")?),
+            })
+        }
+        "wall" => Cell::Wall(color),
+        _ => return Err(format!("Unknown kind \"{kind}\", expected brick or wall")),
+    };
+
+    Ok((c, cell))
+}
+
+fn parse_color(word: &str) -> Result<Vec3, String> {
+    if let Some((_, color)) = NAMED_COLORS.iter().find(|(name, _)| *name == word) {
+        return Ok(*color);
+    }
+
+    let parts: Vec<&str> = word.split(',').collect();
+    let [r, g, b] = parts[..] else {
+        return Err(format!("Unknown color \"{word}\""));
+    };
+
+    Ok(Vec3::new(
+        parse(Some(r), "red")?,
+        parse(Some(g), "green")?,
+        parse(Some(b), "blue")?,
+    ))
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn check_default_level() {
+        let level = Level::default_level();
+        assert_eq!((level.columns, level.rows), (14, 55));
+        assert_eq!(level.paddle_dims, Vec2::new(20., 1.));
+
+        let Cell::Brick(top) = level.cell(0, 0) else {
+            panic!("Expected a brick, but got {:?}", level.cell(0, 0));
+        };
+        assert_eq!(top.color, color::RED);
+        assert_eq!(top.hp, 3);
+
+        let Cell::Brick(bottom) = level.cell(13, 54) else {
+            panic!("Expected a brick, but got {:?}", level.cell(13, 54));
+        };
+        assert_eq!(bottom.color, color::OHNO_PINK);
+        assert_eq!(bottom.hp, 1);
+    }
+
+    #[test]
+    fn check_small_level() {
+        let level = Level::parse(
+            "\
+# Walls in the middle
+brick 3 2
+ball_speed 100
+key R brick red 2 5 0
+key X wall 0.5,0.5,0.5
+
+board
+RXR
+R.R
+",
+        )
+        .unwrap();
+
+        assert_eq!((level.columns, level.rows), (3, 2));
+        assert_eq!(level.brick_dims, Vec2::new(3., 2.));
+        assert_eq!(level.ball_speed, 100.);
+        assert_eq!(level.cell(1, 0), Cell::Wall(Vec3::broadcast(0.5)));
+        assert_eq!(level.cell(1, 1), Cell::Empty);
+        assert!(matches!(
+            level.cell(2, 1),
+            Cell::Brick(BrickType {
+                hp: 2,
+                points: 5,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn check_levels_round_trip() {
+        let level = Level::default_level();
+        assert_eq!(Level::parse(&level.to_text()), Ok(level));
+
+        let mut level = Level::parse("key X wall gray\nboard\n.X.\n").unwrap();
+        level.cells[0] = Cell::Brick(BrickType {
+            color: Vec3::new(0.1, 0.2, 0.3),
+            hp: 4,
+            points: 9,
+            drops: DropTable::even(0.5),
+        });
+        let text = level.to_text();
+        assert!(text.contains("0.1,0.2,0.3 4 9 0.5"), "{text}");
+        assert_eq!(Level::parse(&text), Ok(level));
+    }
+
+    #[test]
+    fn check_bad_levels() {
+        let check = |text: &str, expected: &str| {
+            let e = Level::parse(text).unwrap_err();
+            assert!(
+                e.contains(expected),
+                "\"{e}\" should mention \"{expected}\""
+            );
+        };
+
+        check("size 2 2\n", "board");
+        check("size 2 2\nboard\n..\n", "2x2");
+        check("board\n..\n...\n", "line 3");
+        check("board\n.Q\n", "'Q'");
+        check("key R brick red 0 1 0\nboard\nR\n", "hit point");
+        check("key R brick mauve 1 1 0\nboard\nR\n", "mauve");
+        check("key R wall red\nkey R wall gray\nboard\nR\n", "already");
+        check("paddle 20\nboard\n.\n", "height");
+        check("brick inf 1\nboard\n.\n", "positive");
+        check("brick 1 nan\nboard\n.\n", "positive");
+        check("paddle 1 inf\nboard\n.\n", "positive");
+        check("speed 20\nboard\n.\n", "speed");
+        check("ball_speed 0\nboard\n.\n", "Ball speed");
+        check("ball_speed -5\nboard\n.\n", "Ball speed");
+        check("ball_speed nan\nboard\n.\n", "Ball speed");
+        check("ball_speed inf\nboard\n.\n", "Ball speed");
+    }
+}
@@ -2,21 +2,52 @@
 #![allow(mixed_script_confusables)] // Hell yeah, math!
 #![allow(clippy::nonminimal_bool)] // The compiler can reduce this, let me write it for humans
 
-use fermium::prelude::*;
-use ultraviolet::{Vec2, Vec3};
+use ultraviolet::Vec3;
 
+#[cfg(feature = "gfx")]
+mod app;
+#[cfg(feature = "gfx")]
+pub use app::app_main;
+
+#[cfg(feature = "gfx")]
 mod audio;
-use audio::*;
 
+#[cfg(feature = "gfx")]
 mod gfx;
-use gfx::*;
 
-mod math;
-use math::{sign, Aabb};
+pub mod controller;
+
+pub mod editor;
+
+pub mod env;
+
+pub mod game;
+
+pub mod generate;
+
+pub mod history;
+
+pub mod level;
+
+pub mod math;
+
+pub mod powerup;
+
+pub mod replay;
+
+pub mod rng;
+
+pub mod score;
+
+pub mod snapshot;
+
+pub mod stats;
 
-mod world;
-use world::*;
+pub mod timestep;
 
+pub mod world;
+
+#[cfg(feature = "gfx")]
 embed_plist::embed_info_plist!("../Info.plist");
 
 mod color {
@@ -33,9 +64,14 @@ mod color {
     pub const OHNO_PINK: Vec3 = Vec3::new(1., 0., 1.);
 }
 
-const PADDLE_COLOR: Vec3 = color::WHITE;
+/// One for each player
+const PADDLE_COLORS: [Vec3; 2] = [color::WHITE, color::YELLOW];
 const UNBREAKABLE_BRICK_COLOR: Vec3 = color::GRAY;
 
+/// Size of the window when it's first opened. The board is built to match its aspect ratio.
+pub const WINDOW_WIDTH: i32 = 500;
+pub const WINDOW_HEIGHT: i32 = 750;
+
 /// Returns true when everything is OK and there is no error.
 ///
 /// See [`SDL_GetErrorMsg`](https://wiki.libsdl.org/SDL2/SDL_GetErrorMsg)
@@ -47,6 +83,7 @@ const UNBREAKABLE_BRICK_COLOR: Vec3 = color::GRAY;
 ///     eprintln!("SDL is not happy");
 /// }
 /// ```
+#[cfg(feature = "gfx")]
 pub fn check_sdl_error(func: &str) -> bool {
     // We can't use `c_char` in literals, we HAVE to cast
     #![allow(clippy::unnecessary_cast)]
@@ -77,407 +114,3 @@ pub fn check_sdl_error(func: &str) -> bool {
         false
     }
 }
-
-fn poll_event() -> Option<SDL_Event> {
-    let mut e = SDL_Event::default();
-    if unsafe { SDL_PollEvent(&mut e) == 1 } {
-        Some(e)
-    } else {
-        None
-    }
-}
-
-// This 'static is kind of a lie - values here can and will update while this
-// reference exists. We only read it AFTER calling SDL_PollEvent, so this is safe.
-struct KeyboardState(&'static [u8]);
-
-impl core::ops::Index<SDL_Scancode> for KeyboardState {
-    type Output = u8;
-
-    fn index(&self, index: SDL_Scancode) -> &Self::Output {
-        &self.0[index.0 as usize]
-    }
-}
-
-fn get_keyboard_state() -> KeyboardState {
-    unsafe {
-        let mut num_keys: i32 = 0;
-        let ptr = SDL_GetKeyboardState(&mut num_keys);
-
-        KeyboardState(std::slice::from_raw_parts(ptr, num_keys as usize))
-    }
-}
-
-pub fn app_main() {
-    const SAMPLE_FREQ: u32 = 44_100;
-
-    let waveform1 = SquareWaveform::new(SAMPLE_FREQ, 220);
-    let waveform2 = SawtoothWaveform::new(SAMPLE_FREQ, 220);
-    let _waveform = CombinedWaveforms::new(SAMPLE_FREQ, 1, waveform1, waveform2);
-    let audio_player = AudioPlayer::new(SAMPLE_FREQ, 1, waveform2);
-
-    let window_width: i32 = 500;
-    let window_height: i32 = 750;
-
-    // Initialize graphics & UI
-    let window = Window::new(window_width, window_height);
-    let mut gpu = GpuDevice::new(&window);
-    let mut world = World::default();
-    let mut next = World::default();
-
-    // Shape of a brick & the paddle
-    let dims = Vec2::new(5., 1.);
-
-    // for the board
-    let view_x = (dims.x + 1.) * 14. + 1.;
-    let view_y = view_x * (window_height as f32 / window_width as f32);
-    gpu.set_view(view_x, view_y);
-
-    // Add some unbreakable bricks off screen on the top, left and right
-    {
-        // Side-walls don't move
-        let vel = Vec2::zero();
-        let color = UNBREAKABLE_BRICK_COLOR;
-
-        // Swap the commented `pos` below to move the walls in-frame to see them
-
-        // Left
-        world.unbreakable_bricks.push(Quad {
-            // pos: Vec2::new(0., 0.),
-            pos: Vec2::new(0. - 1., 0.),
-            vel,
-            dims: Vec2::new(1., view_y),
-            color,
-        });
-
-        // Right
-        world.unbreakable_bricks.push(Quad {
-            // pos: Vec2::new(view_x - 1., 0.),
-            pos: Vec2::new(view_x, 0.),
-            vel,
-            dims: Vec2::new(1., view_y),
-            color,
-        });
-
-        // Top
-        world.unbreakable_bricks.push(Quad {
-            // pos: Vec2::new(0., view_y - 1.),
-            pos: Vec2::new(0., view_y),
-            vel,
-            dims: Vec2::new(view_x, 1.),
-            color,
-        });
-    }
-
-    // (x, y) are position in the grid
-    for y in 0..55 {
-        let vel = Vec2::zero();
-        let color: Vec3 = match y {
-            0..=1 => color::RED,
-            2..=3 => color::ORANGE,
-            4..=5 => color::GREEN,
-            6..=7 => color::YELLOW,
-            _ => color::OHNO_PINK,
-        };
-        for x in 0..14 {
-            // Note: Our x coordinate here must match the calculation for view_x above
-            let pos_x = (dims.x + 1.) * (x as f32) + 1.;
-            let pos_y = view_y - (dims.y + 1.) * (y as f32 + 1.);
-            let pos = Vec2::new(pos_x, pos_y);
-
-            world.bricks.push(Quad {
-                pos,
-                vel,
-                dims,
-                color,
-            });
-        }
-    }
-
-    // Add a user-controlled paddle
-    let paddle_pos = Vec2::new(0.5 * view_x - dims.x / 2., 0.05 * view_y);
-    let paddle_dims = Vec2::new(dims.x * 4., dims.y);
-    world.paddle = Quad {
-        pos: paddle_pos,
-        vel: Vec2::zero(),
-        dims: paddle_dims,
-        color: color::WHITE,
-    };
-
-    // Spawn a starter ball
-    let init_ball_pos = paddle_pos + Vec2::new(0.5 * paddle_dims.x - 0.5, 3. * paddle_dims.y);
-    world.create_ball(init_ball_pos);
-
-    let mut paused = false;
-    let mut capture: Option<GpuCapture> = None;
-
-    audio_player.play();
-    window.show();
-
-    'main_loop: loop {
-        let mut paddle_x_vel = 0.;
-
-        // == Handle events ====================================================
-
-        // Pump key presses
-        // Note: Not all key events are handled here.
-        while let Some(e) = poll_event() {
-            // Access to unions is unsafe, so this match block is going to get spicy
-            let type_ = unsafe { e.type_ };
-            match type_ {
-                // Immediately quit everything - unhandled events are forever ignored
-                SDL_QUIT => {
-                    break 'main_loop;
-                }
-
-                SDL_WINDOWEVENT => {
-                    let window_event = unsafe { e.window };
-                    if window_event.event == SDL_WINDOWEVENT_RESIZED {
-                        // gpu will query the drawable size internally, so we don't pass it anything
-                        gpu.on_view_resize();
-                    }
-                }
-
-                SDL_KEYDOWN => {
-                    let key = unsafe { e.key };
-
-                    match key.keysym.sym {
-                        keycode::SDLK_q => {
-                            // Quit the app when "Q" is pressed
-                            break 'main_loop;
-                        }
-
-                        keycode::SDLK_SPACE if key.repeat == 0 => {
-                            // Toggle the simulation update when SPACE is pressed
-                            paused = !paused;
-                        }
-
-                        keycode::SDLK_c => {
-                            // Clear all balls when "C" is pressed
-                            let ball_count = world.balls.len();
-                            world.balls.clear();
-                            println!("Removed {ball_count} balls");
-                        }
-
-                        _ => {}
-                    }
-                }
-
-                SDL_KEYUP => {
-                    let key = unsafe { e.key };
-
-                    match key.keysym.sym {
-                        keycode::SDLK_t if key.repeat == 0 => {
-                            assert!(capture.is_none());
-                            capture = gpu.prepare_capture();
-                        }
-
-                        _ => {}
-                    }
-                }
-                // On tap or drag, spawn a ball!
-                SDL_FINGERDOWN | SDL_FINGERMOTION => {
-                    let _tfinger: SDL_TouchFingerEvent = unsafe { e.tfinger };
-
-                    world.create_ball(init_ball_pos);
-                }
-
-                // Ignore all other events
-                _ => {}
-            }
-        }
-
-        // Simplified interface for per-frame actions that depend on a key being pressed or not.
-        let keyboard = get_keyboard_state();
-
-        if keyboard[SDL_SCANCODE_B] != 0 {
-            // Spawn a ball on the paddle when "B" is pressed
-            let pos = world.paddle.pos + Vec2::new(0.5 * paddle_dims.x - 0.5, 3. * paddle_dims.y);
-            world.create_ball(pos);
-        }
-
-        // == Update gamestate =================================================
-        const DELAY_MS: u32 = 5;
-        let dt = (DELAY_MS as f32) * 1e-3;
-
-        // Advance the simulation
-        if !paused {
-            // audio_player.update_waveform(|waveform| {
-            //     waveform.f -= 0.005;
-            //     if waveform.f < 0. {
-            //         waveform.f = 1.0;
-            //     }
-            // });
-
-            // Update movement from events - this skips the OS keyboard delay
-            const PADDLE_X_VEL: f32 = 400.;
-            if keyboard[SDL_SCANCODE_LEFT] != 0 {
-                paddle_x_vel -= PADDLE_X_VEL;
-            }
-            if keyboard[SDL_SCANCODE_RIGHT] != 0 {
-                paddle_x_vel += PADDLE_X_VEL;
-            }
-
-            // Update the paddle
-            {
-                next.paddle = world.paddle;
-
-                // Update movement
-                next.paddle.pos.x = (world.paddle.pos.x + dt * paddle_x_vel)
-                    // Keep the paddle in bounds
-                    .clamp(0., view_x - paddle_dims.x);
-                // The paddle only slides left & right, so don't modify pos.y
-                next.paddle.pos.y = world.paddle.pos.y;
-            }
-
-            // Update breakable bricks by checking if a ball has hit them
-            // Update ball velocities by checking if they hit a brick OR the paddle -- IN PLACE
-            {
-                fn bounce_against_quad(ball: &mut Ball, brick: &Quad) -> bool {
-                    let radius_sq = ball.radius * ball.radius;
-
-                    let aabb = Aabb::new_from_quad(brick.pos, brick.dims);
-                    let center = aabb.center();
-                    let extents = aabb.half_extents();
-
-                    let dist_clamped = (ball.pos - center).clamped(-extents, extents);
-                    let closest_on_or_in_aabb = center + dist_clamped;
-
-                    if (closest_on_or_in_aabb - ball.pos).mag_sq() < radius_sq {
-                        // TODO: Compute bounce on the ball
-
-                        let x_delta;
-                        let y_delta;
-
-                        if ball.pos.x <= aabb.min.x {
-                            x_delta = ball.pos.x - aabb.min.x;
-                        } else if ball.pos.x >= aabb.max.x {
-                            x_delta = ball.pos.x - aabb.max.x;
-                        } else {
-                            x_delta = ball.radius;
-                        }
-
-                        if ball.pos.y <= aabb.min.y {
-                            y_delta = ball.pos.y - aabb.min.y;
-                        } else if ball.pos.y >= aabb.max.y {
-                            y_delta = ball.pos.y - aabb.max.y;
-                        } else {
-                            y_delta = ball.radius;
-                        }
-
-                        let normal = if x_delta.abs() < y_delta.abs() {
-                            Vec2::new(sign(x_delta), 0.)
-                        } else if y_delta.abs() < x_delta.abs() {
-                            Vec2::new(0., sign(y_delta))
-                        } else {
-                            Vec2::new(-1., -1.).normalized()
-                        };
-
-                        ball.vel = ball.vel.reflected(normal);
-
-                        true
-                    } else {
-                        false
-                    }
-                }
-
-                // Check breakable bricks
-                for brick in &world.bricks {
-                    let mut brick_breaks = false;
-
-                    for ball in &mut world.balls {
-                        // If a ball hit this brick, then it will break
-                        brick_breaks |= bounce_against_quad(ball, brick);
-                    }
-
-                    // If no ball hit this brick, then we delete it (by omission)
-                    if !brick_breaks {
-                        next.bricks.push(*brick);
-                    }
-                }
-
-                // Check UN-breakable bricks
-                for brick in &world.unbreakable_bricks {
-                    for ball in &mut world.balls {
-                        bounce_against_quad(ball, brick);
-                    }
-                }
-
-                // Unbreakable bricks always get copied over
-                next.unbreakable_bricks = std::mem::take(&mut world.unbreakable_bricks);
-
-                for ball in &mut world.balls {
-                    bounce_against_quad(ball, &world.paddle);
-                }
-            }
-
-            // Update all balls' position from velocity
-            {
-                for ball in &world.balls {
-                    let mut next_ball = *ball;
-                    let Ball { pos, vel, .. } = *ball;
-
-                    // Basic physics step
-                    next_ball.pos = pos + dt * vel;
-
-                    // If it's still in bounds, copy it to the next frame
-                    // (TODO: include radius in this math)
-                    if (0. < pos.x && pos.x < view_x) && (0. < pos.y && pos.y < view_y) {
-                        next.balls.push(next_ball);
-                    }
-                }
-            }
-
-            std::mem::swap(&mut world, &mut next);
-            next.reset();
-        }
-
-        // == Render ===========================================================
-        // Draw Quads (this is everything atm)
-        {
-            // Balls
-            for ball in &world.balls {
-                gpu.draw_circle(ball.pos, ball.radius, color::WHITE);
-            }
-
-            // Bricks
-            for brick in &world.bricks {
-                gpu.draw_quad(brick.pos, brick.dims, brick.color);
-            }
-
-            // Unbreakable bricks
-            for brick in &world.unbreakable_bricks {
-                gpu.draw_quad(brick.pos, brick.dims, brick.color);
-            }
-
-            // Paddle
-            gpu.draw_quad(world.paddle.pos, world.paddle.dims, PADDLE_COLOR);
-        }
-
-        gpu.render_and_present();
-
-        if let Some(mut c) = capture.take() {
-            c.mark_frame_done();
-
-            if c.frames_left() != 0 {
-                // oops put it back
-                capture = Some(c);
-            } else {
-                // Pause things, since we're about to switch to viewing the trace
-                paused = true;
-
-                // Finish and view the trace
-                c.stop();
-            }
-        }
-
-        // TODO: Better delay
-        unsafe {
-            SDL_Delay(DELAY_MS);
-        }
-    }
-
-    unsafe {
-        SDL_Quit();
-    }
-}
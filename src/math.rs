@@ -6,11 +6,20 @@ pub struct Aabb {
     pub max: Vec2,
 }
 
+/// Where something hit.
+///
+/// For line tests `pos` is the point on the box, and for circle sweeps it's where the circle's center is at
+/// the moment it touches.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct HitInfo {
     pub t: f32,
     pub pos: Vec2,
+
+    /// Points out of the box, from the face that was hit. Corners get the average of both faces.
     pub normal: Vec2,
+
+    /// This started out overlapping the box. `normal` is for the nearest face.
+    pub inside: bool,
 }
 
 impl Aabb {
@@ -29,6 +38,14 @@ impl Aabb {
         0.5 * (self.max - self.min)
     }
 
+    /// This box grown by `padding` on every side
+    pub fn padded(&self, padding: Vec2) -> Self {
+        Self {
+            min: self.min - padding,
+            max: self.max + padding,
+        }
+    }
+
     pub fn contains_point(&self, point: Vec2) -> bool {
         for i in 0..2 {
             if (self.min[i] <= point[i]) && (point[i] <= self.max[i]) {
@@ -78,11 +95,31 @@ impl Aabb {
         }
 
         if inside {
-            // It's already intersecting
+            // It's already intersecting, so report the face that's closest to the origin.
+            // When faces are tied (e.g. from the center), pick the one the line is heading towards.
+            let faces = [
+                (origin.x - min.x, Vec2::new(-1., 0.)),
+                (max.x - origin.x, Vec2::new(1., 0.)),
+                (origin.y - min.y, Vec2::new(0., -1.)),
+                (max.y - origin.y, Vec2::new(0., 1.)),
+            ];
+
+            let mut normal = faces[0].1;
+            let mut nearest = faces[0].0;
+            for (dist, face_normal) in faces {
+                let closer = dist < nearest;
+                let tied = dist == nearest && face_normal.dot(dir) > normal.dot(dir);
+                if closer || tied {
+                    nearest = dist;
+                    normal = face_normal;
+                }
+            }
+
             return Some(HitInfo {
                 t: 0.,
-                pos: origin,  // the hit location is the origin
-                normal: -dir, // the normal is the opposite of the direction?
+                pos: origin, // the hit location is the origin
+                normal,
+                inside: true,
             });
         }
 
@@ -98,15 +135,31 @@ impl Aabb {
 
         // Final candidate:
         let t = t_max.component_max();
+        if t < 0. {
+            // The line is pointing away from the box
+            return None;
+        }
 
         let pos = origin + t * dir;
-        let normal = planes.normalized(); // ???
-
         if !(Aabb { min, max }).contains_point(pos) {
             return None;
         }
 
-        Some(HitInfo { t, pos, normal })
+        // The normal points out of whichever plane(s) we hit last. Hitting both at once is a corner.
+        let mut normal = Vec2::zero();
+        for i in 0..2 {
+            if quadrant[i] != Q_MIDDLE && t_max[i] == t {
+                normal[i] = if quadrant[i] == Q_LEFT { -1. } else { 1. };
+            }
+        }
+        let normal = normal.normalized();
+
+        Some(HitInfo {
+            t,
+            pos,
+            normal,
+            inside: false,
+        })
     }
 
     pub fn intersects_with_aabb(&self, aabb: &Self) -> bool {
@@ -122,6 +175,269 @@ impl Aabb {
         // To perform a sweep, we'll pad out the other box with our half extents, and use a line test
         self.intersects_with_line(aabb.center(), sweep, Some(aabb.half_extents()))
     }
+
+    /// Sweep a circle from `center` along `motion`, and find where it first touches this box.
+    ///
+    /// `t` is the fraction of `motion` covered before touching, from 0 to 1. A circle that starts out
+    /// overlapping the box hits at `t = 0`, with `pos` pushed out to where it's just touching.
+    /// Circles that are moving away from the box never hit it.
+    pub fn intersects_with_circle_sweep(
+        &self,
+        center: Vec2,
+        radius: f32,
+        motion: Vec2,
+    ) -> Option<HitInfo> {
+        // Check if we're already overlapping
+        let closest = center.clamped(self.min, self.max);
+        let offset = center - closest;
+        let dist_sq = offset.mag_sq();
+        if dist_sq < radius * radius {
+            let (normal, pos) = if dist_sq > 0. {
+                let normal = offset / dist_sq.sqrt();
+                (normal, closest + radius * normal)
+            } else {
+                // The center is inside of the box, so push it out through the nearest face
+                let to_min = center - self.min;
+                let to_max = self.max - center;
+                let nearest = to_min.component_min().min(to_max.component_min());
+
+                let mut pos = center;
+                let normal = if nearest == to_min.x {
+                    pos.x = self.min.x - radius;
+                    Vec2::new(-1., 0.)
+                } else if nearest == to_max.x {
+                    pos.x = self.max.x + radius;
+                    Vec2::new(1., 0.)
+                } else if nearest == to_min.y {
+                    pos.y = self.min.y - radius;
+                    Vec2::new(0., -1.)
+                } else {
+                    pos.y = self.max.y + radius;
+                    Vec2::new(0., 1.)
+                };
+                (normal, pos)
+            };
+
+            if motion.dot(normal) >= 0. {
+                return None;
+            }
+
+            return Some(HitInfo {
+                t: 0.,
+                pos,
+                normal,
+                inside: true,
+            });
+        }
+
+        // Otherwise, sweep against the box grown by our radius
+        let hit = self.intersects_with_line(center, motion, Some(Vec2::broadcast(radius)))?;
+        if !(0. ..=1.).contains(&hit.t) {
+            return None;
+        }
+
+        // The grown box has square corners, but a circle can only get `radius` away from the real corner.
+        // If we hit the grown box next to a corner, check against a circle around that corner instead.
+        let corner = hit.pos.clamped(self.min, self.max);
+        if corner.x != hit.pos.x && corner.y != hit.pos.y {
+            let t = line_circle_t(center, motion, corner, radius)?;
+            if !(0. ..=1.).contains(&t) {
+                return None;
+            }
+
+            let pos = center + t * motion;
+            let normal = (pos - corner).normalized();
+
+            return Some(HitInfo {
+                t,
+                pos,
+                normal,
+                inside: false,
+            });
+        }
+
+        // Otherwise it's on a face. Sliding along a face or leaving it isn't a hit.
+        if motion.dot(hit.normal) >= 0. {
+            return None;
+        }
+
+        Some(HitInfo {
+            inside: false,
+            ..hit
+        })
+    }
+}
+
+/// First `t` where `origin + t * dir` is `radius` away from `center`, if it ever is
+fn line_circle_t(origin: Vec2, dir: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let m = origin - center;
+
+    let a = dir.mag_sq();
+    let b = m.dot(dir);
+    let c = m.mag_sq() - radius * radius;
+
+    if a == 0. {
+        return None;
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0. {
+        return None;
+    }
+
+    Some((-b - discriminant.sqrt()) / a)
+}
+
+/// Buckets boxes into a grid of same-sized cells, to find the ones near an area without checking every box.
+///
+/// The grid covers a fixed area. Boxes and queries that poke outside of it are clamped onto the edge cells, so
+/// they still work, just less efficiently. Areas too big for [`MAX_GRID_CELLS`] get bigger cells, and ones
+/// that aren't finite get a single cell.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UniformGrid {
+    origin: Vec2,
+    cell_size: Vec2,
+    cols: usize,
+    rows: usize,
+
+    /// Ids in cell `c` are `items[cell_start[c]..cell_start[c + 1]]`
+    cell_start: Vec<u32>,
+    items: Vec<u32>,
+
+    /// Number of boxes in the grid
+    len: usize,
+}
+
+/// Most cells a [`UniformGrid`] will have, however big an area it covers
+pub const MAX_GRID_CELLS: usize = 1 << 16;
+
+impl UniformGrid {
+    /// Build a grid covering `bounds`. Each box's id is its position in `boxes`.
+    pub fn new(
+        bounds: Aabb,
+        cell_size: Vec2,
+        boxes: impl IntoIterator<Item = Aabb> + Clone,
+    ) -> Self {
+        let mut grid = Self::default();
+        grid.rebuild(bounds, cell_size, boxes);
+
+        grid
+    }
+
+    /// Same as [`UniformGrid::new()`], but reuses this grid's allocations
+    pub fn rebuild(
+        &mut self,
+        bounds: Aabb,
+        cell_size: Vec2,
+        boxes: impl IntoIterator<Item = Aabb> + Clone,
+    ) {
+        assert!(cell_size.x > 0. && cell_size.y > 0.);
+
+        // Count cells as floats, so huge areas can't overflow before we check them
+        let size = bounds.max - bounds.min;
+        let counts = |cell_size: Vec2| {
+            let cols = (size.x / cell_size.x).ceil().max(1.);
+            let rows = (size.y / cell_size.y).ceil().max(1.);
+            (cols, rows)
+        };
+        let mut cell_size = cell_size;
+        let (mut cols, mut rows) = counts(cell_size);
+        if !(cols * rows).is_finite() {
+            cell_size = Vec2::broadcast(f32::INFINITY);
+            (cols, rows) = (1., 1.);
+        }
+        while cols * rows > MAX_GRID_CELLS as f32 {
+            cell_size *= 2.;
+            (cols, rows) = counts(cell_size);
+        }
+
+        self.origin = bounds.min;
+        self.cell_size = cell_size;
+        self.cols = cols as usize;
+        self.rows = rows as usize;
+
+        // Count how many boxes land in each cell, then turn that into offsets
+        self.cell_start.clear();
+        self.cell_start.resize(self.cols * self.rows + 1, 0);
+        self.len = 0;
+        for aabb in boxes.clone() {
+            self.len += 1;
+            for cell in self.cells(&aabb) {
+                self.cell_start[cell + 1] += 1;
+            }
+        }
+        for cell in 1..self.cell_start.len() {
+            self.cell_start[cell] += self.cell_start[cell - 1];
+        }
+
+        // Then drop each id into place. `cursor` tracks the next free slot in each cell.
+        let mut cursor = self.cell_start.clone();
+        self.items.clear();
+        self.items
+            .resize(self.cell_start[self.cols * self.rows] as usize, 0);
+        for (id, aabb) in boxes.into_iter().enumerate() {
+            for cell in self.cells(&aabb) {
+                self.items[cursor[cell] as usize] = id as u32;
+                cursor[cell] += 1;
+            }
+        }
+    }
+
+    /// Number of boxes in the grid
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Collect the ids of every box that might overlap `region` into `out`, without duplicates.
+    ///
+    /// This can include boxes that don't actually overlap, so check them afterwards.
+    pub fn query(&self, region: &Aabb, out: &mut Vec<u32>) {
+        out.clear();
+        if self.cell_start.is_empty() {
+            return;
+        }
+
+        for cell in self.cells(region) {
+            let range = self.cell_start[cell] as usize..self.cell_start[cell + 1] as usize;
+            out.extend_from_slice(&self.items[range]);
+        }
+
+        // Boxes that span a few cells show up once per cell
+        out.sort_unstable();
+        out.dedup();
+    }
+
+    /// Every cell that `aabb` touches
+    fn cells(&self, aabb: &Aabb) -> impl Iterator<Item = usize> {
+        let cols = self.cols;
+        let (x_range, y_range) = self.cell_ranges(aabb);
+
+        y_range.flat_map(move |y| x_range.clone().map(move |x| y * cols + x))
+    }
+
+    fn cell_ranges(
+        &self,
+        aabb: &Aabb,
+    ) -> (
+        std::ops::RangeInclusive<usize>,
+        std::ops::RangeInclusive<usize>,
+    ) {
+        let cell = |p: Vec2| {
+            let c = (p - self.origin) / self.cell_size;
+            let x = (c.x.floor().max(0.) as usize).min(self.cols - 1);
+            let y = (c.y.floor().max(0.) as usize).min(self.rows - 1);
+            (x, y)
+        };
+
+        let (x0, y0) = cell(aabb.min);
+        let (x1, y1) = cell(aabb.max);
+
+        (x0..=x1, y0..=y1)
+    }
 }
 
 pub fn sign(x: f32) -> f32 {
@@ -161,6 +477,7 @@ mod t {
                     t: 0.5,
                     pos: Vec2::new(-0.5, 0.),
                     normal: Vec2::new(-1., 0.),
+                    inside: false,
                 }),
             );
         }
@@ -174,6 +491,7 @@ mod t {
                     t: 0.5,
                     pos: Vec2::new(0.5, 0.),
                     normal: Vec2::new(1., 0.),
+                    inside: false,
                 }),
             );
         }
@@ -187,6 +505,7 @@ mod t {
                     t: 0.5,
                     pos: Vec2::new(0., 0.5),
                     normal: Vec2::new(0., 1.),
+                    inside: false,
                 }),
             );
         }
@@ -200,6 +519,7 @@ mod t {
                     t: 0.5,
                     pos: Vec2::new(0., -0.5),
                     normal: Vec2::new(0., -1.),
+                    inside: false,
                 }),
             );
         }
@@ -213,6 +533,7 @@ mod t {
                     t: 0.5,
                     pos: Vec2::new(-0.5, 0.5),
                     normal: Vec2::new(-1., 1.).normalized(),
+                    inside: false,
                 }),
             );
         }
@@ -226,7 +547,8 @@ mod t {
                 Some(HitInfo {
                     t: 0.,
                     pos: Vec2::new(0., 0.),
-                    normal: -Vec2::new(1., 0.),
+                    normal: Vec2::new(1., 0.),
+                    inside: true,
                 }),
             );
         }
@@ -239,7 +561,8 @@ mod t {
                 Some(HitInfo {
                     t: 0.,
                     pos: Vec2::new(0., 0.),
-                    normal: -Vec2::new(-1., 0.),
+                    normal: Vec2::new(-1., 0.),
+                    inside: true,
                 }),
             );
         }
@@ -252,7 +575,8 @@ mod t {
                 Some(HitInfo {
                     t: 0.,
                     pos: Vec2::new(0., 0.),
-                    normal: -Vec2::new(0., -1.),
+                    normal: Vec2::new(0., -1.),
+                    inside: true,
                 }),
             );
         }
@@ -265,7 +589,8 @@ mod t {
                 Some(HitInfo {
                     t: 0.,
                     pos: Vec2::new(0., 0.),
-                    normal: -Vec2::new(0., 1.),
+                    normal: Vec2::new(0., 1.),
+                    inside: true,
                 }),
             );
         }
@@ -291,6 +616,7 @@ mod t {
                     t: 0.25,
                     pos: Vec2::new(-0.75, 0.),
                     normal: Vec2::new(-1., 0.),
+                    inside: false,
                 }),
             );
         }
@@ -308,6 +634,7 @@ mod t {
                     t: 0.25,
                     pos: Vec2::new(0.75, 0.),
                     normal: Vec2::new(1., 0.),
+                    inside: false,
                 }),
             );
         }
@@ -325,6 +652,7 @@ mod t {
                     t: 0.25,
                     pos: Vec2::new(0., 0.75),
                     normal: Vec2::new(0., 1.),
+                    inside: false,
                 }),
             );
         }
@@ -342,6 +670,7 @@ mod t {
                     t: 0.25,
                     pos: Vec2::new(0., -0.75),
                     normal: Vec2::new(0., -1.),
+                    inside: false,
                 }),
             );
         }
@@ -358,15 +687,308 @@ mod t {
                 Some(HitInfo {
                     t: 0.25,
                     pos: Vec2::new(-0.75, 0.75),
-                    // Floating point rounding gets us here, so we have to type out this exactly
-                    // normal: Vec2::new(-0.7071068, 0.7071068).normalized(),
-                    normal: Vec2::new(
-                        -std::f32::consts::FRAC_1_SQRT_2,
-                        std::f32::consts::FRAC_1_SQRT_2
-                    )
-                    .normalized(),
+                    // Padding doesn't change the corner, so this is the same as without it
+                    normal: Vec2::new(-1., 1.).normalized(),
+                    inside: false,
                 }),
             );
         }
     }
+
+    #[cfg(test)]
+    mod check_intersects_with_line_off_axis {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        // Tall box that's nowhere near the origin
+        const BOX: Aabb = Aabb {
+            min: Vec2::new(2., 3.),
+            max: Vec2::new(4., 7.),
+        };
+
+        #[test]
+        fn check_left_face() {
+            // Line starting LEFT of the box, moving RIGHT and a bit UP
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(0., 4.), Vec2::new(1., 0.5), None),
+                Some(HitInfo {
+                    t: 2.,
+                    pos: Vec2::new(2., 5.),
+                    normal: Vec2::new(-1., 0.),
+                    inside: false,
+                }),
+            );
+        }
+
+        #[test]
+        fn check_top_face() {
+            // Line starting ABOVE the box, moving DOWN and a bit RIGHT
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(3., 10.), Vec2::new(0.5, -2.), None),
+                Some(HitInfo {
+                    t: 1.5,
+                    pos: Vec2::new(3.75, 7.),
+                    normal: Vec2::new(0., 1.),
+                    inside: false,
+                }),
+            );
+        }
+
+        #[test]
+        fn check_right_face_shallow() {
+            // Line starting RIGHT of and BELOW the box, mostly moving LEFT. It's below the bottom plane
+            // when it starts, but crosses it before it reaches the right plane.
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(8., 2.5), Vec2::new(-2., 0.5), None),
+                Some(HitInfo {
+                    t: 2.,
+                    pos: Vec2::new(4., 3.5),
+                    normal: Vec2::new(1., 0.),
+                    inside: false,
+                }),
+            );
+        }
+
+        #[test]
+        fn check_corner_hit() {
+            // Line starting BELOW and LEFT of the box, moving UP and RIGHT into the corner
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(0., 1.), Vec2::new(2., 2.), None),
+                Some(HitInfo {
+                    t: 1.,
+                    pos: Vec2::new(2., 3.),
+                    normal: Vec2::new(-1., -1.).normalized(),
+                    inside: false,
+                }),
+            );
+        }
+
+        #[test]
+        fn check_misses() {
+            // Passes underneath
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(0., 0.), Vec2::new(1., 0.), None),
+                None
+            );
+
+            // Would hit, but it's pointing the wrong way
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(0., 4.), Vec2::new(-1., 0.5), None),
+                None
+            );
+
+            // Passes just over the corner
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(0., 6.), Vec2::new(1., 1.), None),
+                None
+            );
+        }
+
+        #[test]
+        fn check_inside_nearest_face() {
+            // Starting inside, the normal is for the closest face - not where the line is going
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(3.5, 5.), Vec2::new(0., 1.), None),
+                Some(HitInfo {
+                    t: 0.,
+                    pos: Vec2::new(3.5, 5.),
+                    normal: Vec2::new(1., 0.),
+                    inside: true,
+                }),
+            );
+
+            assert_eq!(
+                BOX.intersects_with_line(Vec2::new(3., 3.5), Vec2::new(1., 1.), None),
+                Some(HitInfo {
+                    t: 0.,
+                    pos: Vec2::new(3., 3.5),
+                    normal: Vec2::new(0., -1.),
+                    inside: true,
+                }),
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod check_intersects_with_circle_sweep {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        const RADIUS: f32 = 0.25;
+
+        #[test]
+        fn check_face_hit() {
+            // Circle starting LEFT of the box and moving RIGHT
+            assert_eq!(
+                UNIT_AABB.intersects_with_circle_sweep(
+                    Vec2::new(-1.75, 0.),
+                    RADIUS,
+                    Vec2::new(2., 0.)
+                ),
+                Some(HitInfo {
+                    t: 0.5,
+                    pos: Vec2::new(-0.75, 0.),
+                    normal: Vec2::new(-1., 0.),
+                    inside: false,
+                }),
+            );
+        }
+
+        #[test]
+        fn check_fast_circle_does_not_tunnel() {
+            // Circle starting BELOW the box, moving UP so fast that it would end up past it
+            let hit = UNIT_AABB.intersects_with_circle_sweep(
+                Vec2::new(0.25, -2.),
+                RADIUS,
+                Vec2::new(0., 100.),
+            );
+
+            let hit = hit.expect("Circle went right through the box");
+            assert!((hit.pos - Vec2::new(0.25, -0.75)).mag() < 1e-5);
+            assert_eq!(hit.normal, Vec2::new(0., -1.));
+        }
+
+        #[test]
+        fn check_corner_hit() {
+            // Circle starting ABOVE and LEFT of the box, moving DOWN and to the RIGHT, at the corner
+            let hit = UNIT_AABB
+                .intersects_with_circle_sweep(Vec2::new(-1., 1.), RADIUS, Vec2::new(1., -1.))
+                .unwrap();
+
+            let corner = Vec2::new(-0.5, 0.5);
+            assert!(((hit.pos - corner).mag() - RADIUS).abs() < 1e-5);
+            assert!((hit.normal - Vec2::new(-1., 1.).normalized()).mag() < 1e-5);
+        }
+
+        #[test]
+        fn check_corner_miss() {
+            // Circle that would clip the grown box's square corner, but not the round one
+            assert_eq!(
+                UNIT_AABB.intersects_with_circle_sweep(
+                    Vec2::new(-1.5, -0.1),
+                    RADIUS,
+                    Vec2::new(1., 1.)
+                ),
+                None,
+            );
+        }
+
+        #[test]
+        fn check_too_short() {
+            // Circle moving towards the box, but not far enough to touch it
+            assert_eq!(
+                UNIT_AABB.intersects_with_circle_sweep(
+                    Vec2::new(-2., 0.),
+                    RADIUS,
+                    Vec2::new(1., 0.)
+                ),
+                None,
+            );
+        }
+
+        #[test]
+        fn check_starts_overlapping() {
+            // Circle overlapping the top face and moving down into it gets pushed back out
+            assert_eq!(
+                UNIT_AABB.intersects_with_circle_sweep(
+                    Vec2::new(0., 0.625),
+                    RADIUS,
+                    Vec2::new(0., -1.)
+                ),
+                Some(HitInfo {
+                    t: 0.,
+                    pos: Vec2::new(0., 0.75),
+                    normal: Vec2::new(0., 1.),
+                    inside: true,
+                }),
+            );
+
+            // ... unless it's already leaving
+            assert_eq!(
+                UNIT_AABB.intersects_with_circle_sweep(
+                    Vec2::new(0., 0.625),
+                    RADIUS,
+                    Vec2::new(0., 1.)
+                ),
+                None,
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod check_uniform_grid {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn query(grid: &UniformGrid, min: Vec2, max: Vec2) -> Vec<u32> {
+            let mut out = vec![];
+            grid.query(&Aabb { min, max }, &mut out);
+            out
+        }
+
+        #[test]
+        fn check_query_finds_nearby_boxes() {
+            let bounds = Aabb {
+                min: Vec2::new(0., 0.),
+                max: Vec2::new(10., 10.),
+            };
+            let boxes = [
+                // Bottom left
+                Aabb::new_from_quad(Vec2::new(1., 1.), Vec2::new(1., 1.)),
+                // Top right
+                Aabb::new_from_quad(Vec2::new(8., 8.), Vec2::new(1., 1.)),
+                // Spans the whole bottom row
+                Aabb::new_from_quad(Vec2::new(0., 0.), Vec2::new(10., 0.5)),
+                // Outside of the grid on the left
+                Aabb::new_from_quad(Vec2::new(-2., 4.), Vec2::new(1., 2.)),
+            ];
+            let grid = UniformGrid::new(bounds, Vec2::new(2., 2.), boxes);
+            assert_eq!(grid.len(), 4);
+
+            assert_eq!(
+                query(&grid, Vec2::new(1.5, 1.5), Vec2::new(1.6, 1.6)),
+                [0, 2]
+            );
+            assert_eq!(query(&grid, Vec2::new(8.5, 8.5), Vec2::new(8.6, 8.6)), [1]);
+            assert_eq!(query(&grid, Vec2::new(5., 5.), Vec2::new(5., 5.)), [0; 0]);
+
+            // Everything, each only once
+            assert_eq!(
+                query(&grid, Vec2::new(-5., -5.), Vec2::new(15., 15.)),
+                [0, 1, 2, 3]
+            );
+
+            // Past the edge of the grid clamps back onto it
+            assert_eq!(query(&grid, Vec2::new(-4., 5.), Vec2::new(-3., 5.)), [3]);
+        }
+
+        #[test]
+        fn check_huge_grids() {
+            let boxes = [Aabb::new_from_quad(Vec2::new(1., 1.), Vec2::new(1., 1.))];
+            let huge = Aabb {
+                min: Vec2::new(0., 0.),
+                max: Vec2::new(1e30, 1e30),
+            };
+            let grid = UniformGrid::new(huge, Vec2::new(1., 1.), boxes);
+            assert!(grid.cols * grid.rows <= MAX_GRID_CELLS);
+            assert_eq!(query(&grid, Vec2::new(1.5, 1.5), Vec2::new(1.6, 1.6)), [0]);
+
+            for max in [Vec2::new(f32::INFINITY, 10.), Vec2::new(f32::NAN, f32::NAN)] {
+                let bounds = Aabb {
+                    min: Vec2::new(0., 0.),
+                    max,
+                };
+                let grid = UniformGrid::new(bounds, Vec2::new(1., 1.), boxes);
+                assert_eq!((grid.cols, grid.rows), (1, 1));
+                assert_eq!(query(&grid, Vec2::new(1.5, 1.5), Vec2::new(1.6, 1.6)), [0]);
+            }
+        }
+
+        #[test]
+        fn check_empty_grid() {
+            let grid = UniformGrid::default();
+            assert!(grid.is_empty());
+            assert_eq!(query(&grid, Vec2::new(0., 0.), Vec2::new(1., 1.)), [0; 0]);
+        }
+    }
 }
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use ultraviolet::{Vec2, Vec3};
+
+use crate::color;
+use crate::rng::Pcg32;
+use crate::world::Quad;
+
+/// Everything a capsule can give the player when the paddle catches it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    /// Every ball in play splits into three
+    MultiBall,
+
+    /// The paddle gets wider for a while
+    WidePaddle,
+
+    /// Balls move slower for a while
+    SlowBall,
+
+    /// The paddle shoots lasers that damage bricks for a while
+    Laser,
+
+    /// Balls stick to the paddle when they land on it for a while, and launch again after a moment
+    StickyPaddle,
+}
+
+impl PowerUpKind {
+    pub const COUNT: usize = 5;
+
+    pub const ALL: [PowerUpKind; Self::COUNT] = [
+        PowerUpKind::MultiBall,
+        PowerUpKind::WidePaddle,
+        PowerUpKind::SlowBall,
+        PowerUpKind::Laser,
+        PowerUpKind::StickyPaddle,
+    ];
+
+    /// Color of the capsules that hold this
+    pub fn color(self) -> Vec3 {
+        match self {
+            PowerUpKind::MultiBall => color::WHITE,
+            PowerUpKind::WidePaddle => color::GREEN,
+            PowerUpKind::SlowBall => color::YELLOW,
+            PowerUpKind::Laser => color::RED,
+            PowerUpKind::StickyPaddle => color::ORANGE,
+        }
+    }
+}
+
+/// What a brick drops when it breaks, and how often.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DropTable {
+    /// Chance from 0 to 1 that the brick drops anything at all
+    pub chance: f32,
+
+    /// Relative odds of each kind of power-up, indexed by `PowerUpKind as usize`
+    pub weights: [u32; PowerUpKind::COUNT],
+}
+
+impl DropTable {
+    /// Never drops anything
+    pub const NONE: DropTable = DropTable {
+        chance: 0.,
+        weights: [0; PowerUpKind::COUNT],
+    };
+
+    /// Drops every kind of power-up with the same odds
+    pub fn even(chance: f32) -> Self {
+        Self {
+            chance,
+            weights: [1; PowerUpKind::COUNT],
+        }
+    }
+
+    /// Pick what to drop, if anything
+    pub fn roll(&self, rng: &mut Pcg32) -> Option<PowerUpKind> {
+        use rand::prelude::*;
+
+        let total: u32 = self.weights.iter().sum();
+        if total == 0 || rng.random::<f32>() >= self.chance {
+            return None;
+        }
+
+        let mut pick = rng.random_range(0..total);
+        for (kind, &weight) in PowerUpKind::ALL.iter().zip(&self.weights) {
+            if pick < weight {
+                return Some(*kind);
+            }
+            pick -= weight;
+        }
+
+        unreachable!("Picked past the end of the drop table")
+    }
+}
+
+/// A power-up falling towards the paddle
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Capsule {
+    pub kind: PowerUpKind,
+    pub quad: Quad,
+}
+
+/// Size of a capsule. They're a bit smaller than a brick.
+pub const CAPSULE_DIMS: Vec2 = Vec2::new(3., 1.);
+
+impl Capsule {
+    /// A capsule centered on `center`, falling at `speed`
+    pub fn new(kind: PowerUpKind, center: Vec2, speed: f32) -> Self {
+        Self {
+            kind,
+            quad: Quad {
+                pos: center - 0.5 * CAPSULE_DIMS,
+                vel: Vec2::new(0., -speed),
+                dims: CAPSULE_DIMS,
+                color: kind.color(),
+            },
+        }
+    }
+}
+
+/// Seconds left on each timed power-up. Anything at 0 isn't active.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Effects {
+    pub wide_paddle: f32,
+    pub slow_ball: f32,
+    pub laser: f32,
+    pub sticky_paddle: f32,
+
+    /// Seconds until the paddle fires its next pair of lasers
+    pub laser_cooldown: f32,
+}
+
+impl Effects {
+    /// Count down every timer by `dt`
+    pub fn ticked(&self, dt: f32) -> Self {
+        let tick = |t: f32| (t - dt).max(0.);
+
+        Self {
+            wide_paddle: tick(self.wide_paddle),
+            slow_ball: tick(self.slow_ball),
+            laser: tick(self.laser),
+            sticky_paddle: tick(self.sticky_paddle),
+            laser_cooldown: tick(self.laser_cooldown),
+        }
+    }
+
+    /// Start (or restart) the timer for a power-up. Power-ups that happen all at once don't have a timer.
+    pub fn start(&mut self, kind: PowerUpKind, duration: f32) {
+        match kind {
+            PowerUpKind::MultiBall => {}
+            PowerUpKind::WidePaddle => self.wide_paddle = duration,
+            PowerUpKind::SlowBall => self.slow_ball = duration,
+            PowerUpKind::Laser => self.laser = duration,
+            PowerUpKind::StickyPaddle => self.sticky_paddle = duration,
+        }
+    }
+}
+
+/// Tunables for power-ups
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpRules {
+    /// How fast capsules fall, in units per second
+    pub capsule_speed: f32,
+
+    /// How long timed power-ups last, in seconds
+    pub duration: f32,
+
+    /// How much wider the paddle gets
+    pub wide_paddle_scale: f32,
+
+    /// How much slower balls go
+    pub slow_ball_scale: f32,
+
+    /// Seconds between each pair of lasers
+    pub laser_interval: f32,
+
+    /// How fast lasers go up, in units per second
+    pub laser_speed: f32,
+
+    /// How long balls stay stuck to a sticky paddle, in seconds
+    pub sticky_hold: f32,
+
+    /// Angle between the balls that a ball splits into, in radians
+    pub multi_ball_spread: f32,
+
+    /// Multi-ball stops splitting balls once there are this many in play
+    pub multi_ball_limit: usize,
+}
+
+impl Default for PowerUpRules {
+    fn default() -> Self {
+        Self {
+            capsule_speed: 30.,
+            duration: 10.,
+            wide_paddle_scale: 1.5,
+            slow_ball_scale: 0.6,
+            laser_interval: 0.5,
+            laser_speed: 150.,
+            sticky_hold: 1.,
+            multi_ball_spread: std::f32::consts::PI / 8.,
+            multi_ball_limit: 256,
+        }
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn check_drop_table_odds() {
+        let mut rng = Pcg32::new(0xb0a7);
+
+        assert_eq!(DropTable::NONE.roll(&mut rng), None);
+
+        // Only lasers, every time
+        let mut weights = [0; PowerUpKind::COUNT];
+        weights[PowerUpKind::Laser as usize] = 1;
+        let lasers = DropTable {
+            chance: 1.,
+            weights,
+        };
+        for _ in 0..100 {
+            assert_eq!(lasers.roll(&mut rng), Some(PowerUpKind::Laser));
+        }
+
+        // Roughly a quarter of the time, and every kind shows up
+        let table = DropTable::even(0.25);
+        let mut counts = [0; PowerUpKind::COUNT];
+        let mut drops = 0;
+        for _ in 0..10_000 {
+            if let Some(kind) = table.roll(&mut rng) {
+                counts[kind as usize] += 1;
+                drops += 1;
+            }
+        }
+        assert!((2_250..2_750).contains(&drops), "{drops} drops");
+        assert!(counts.iter().all(|&count| count > 0), "{counts:?}");
+    }
+}
@@ -0,0 +1,398 @@
+//! Every tick's input from a game, so it can be played back exactly the way it happened.
+//!
+//! The simulation only depends on the level, the seed and the inputs, so that's all a replay needs. Replay
+//! files are plain text:
+//!
+//! ```text
+//! breakout-replay 3
+//! seed 1234
+//! tick_rate 120
+//! aspect 1.5
+//! inputs S L*40 .*12 P*300 R*7 LB
+//! inputs R*100
+//! level
+//! size 14 55
+//! ...
+//! ```
+//!
+//! Inputs are `<keys>*<ticks>` runs, with `*<ticks>` left off when it's just one. Keys are `L` (left), `R`
+//! (right), `B` (spawn a ball), `C` (clear balls), `S` (launch), `P` (paused, so the tick doesn't step the
+//! game) and `.` (nothing). There can be as many `inputs` lines as it takes. Everything after `level` is
+//! the board, in the format from [`crate::level`].
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::game::Game;
+use crate::level::Level;
+use crate::world::FrameInput;
+
+/// Bumped whenever old replay files stop making sense
+pub const REPLAY_VERSION: u32 = 3;
+
+/// The same input for a number of ticks in a row
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Run {
+    pub input: FrameInput,
+
+    /// The game was paused, so these ticks went by without stepping it
+    pub paused: bool,
+
+    pub ticks: u32,
+}
+
+/// What it takes to play a game again
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub tick_rate: f32,
+    pub aspect: f32,
+    pub level: Level,
+    pub runs: Vec<Run>,
+}
+
+impl Replay {
+    /// Start recording a game that starts like `Game::from_level(level, aspect, seed)` and ticks at
+    /// `tick_rate`
+    pub fn new(level: Level, aspect: f32, seed: u64, tick_rate: f32) -> Self {
+        Self {
+            seed,
+            tick_rate,
+            aspect,
+            level,
+            runs: vec![],
+        }
+    }
+
+    /// The game as it was before the first tick
+    pub fn game(&self) -> Game {
+        Game::from_level(Arc::new(self.level.clone()), self.aspect, self.seed)
+    }
+
+    /// Add a tick to the end of the replay
+    pub fn record(&mut self, input: &FrameInput, paused: bool) {
+        match self.runs.last_mut() {
+            Some(run) if run.input == *input && run.paused == paused && run.ticks < u32::MAX => {
+                run.ticks += 1;
+            }
+            _ => self.runs.push(Run {
+                input: *input,
+                paused,
+                ticks: 1,
+            }),
+        }
+    }
+
+    /// Forget everything after the first `played_ticks` ticks that stepped the game, like when the game gets
+    /// rewound to that point
+    pub fn truncate(&mut self, played_ticks: u64) {
+        let mut played = 0;
+        for (i, run) in self.runs.iter_mut().enumerate() {
+            if run.paused {
+                continue;
+            }
+
+            if played + run.ticks as u64 >= played_ticks {
+                run.ticks = (played_ticks - played) as u32;
+                let keep = if run.ticks == 0 { i } else { i + 1 };
+                self.runs.truncate(keep);
+                return;
+            }
+            played += run.ticks as u64;
+        }
+    }
+
+    /// Every tick's input, and whether the game was paused for it
+    pub fn inputs(&self) -> impl Iterator<Item = (FrameInput, bool)> + '_ {
+        self.runs
+            .iter()
+            .flat_map(|run| std::iter::repeat_n((run.input, run.paused), run.ticks as usize))
+    }
+
+    /// Ticks that stepped the game
+    pub fn played_ticks(&self) -> u64 {
+        let played = self.runs.iter().filter(|run| !run.paused);
+        played.map(|run| run.ticks as u64).sum()
+    }
+
+    /// Play the whole thing back as fast as we can, and return how the game ended up
+    pub fn play(&self) -> Game {
+        let dt = 1. / self.tick_rate;
+        let mut game = self.game();
+        let mut next = Game::default();
+
+        for (input, paused) in self.inputs() {
+            if !paused {
+                game.step_into(&input, dt, &mut next);
+                std::mem::swap(&mut game, &mut next);
+            }
+        }
+
+        game
+    }
+
+    /// Read and parse a replay file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_text())
+            .map_err(|e| format!("Couldn't write {}: {e}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        match lines
+            .next()
+            .map(|(_, line)| line.split_whitespace().collect::<Vec<_>>())
+        {
+            Some(words) if words.first() == Some(&"breakout-replay") => {
+                let version: u32 = parse(words.get(1).copied(), "version")?;
+                if version != REPLAY_VERSION {
+                    return Err(format!(
+                        "Replay version {version} isn't supported (expected {REPLAY_VERSION})"
+                    ));
+                }
+            }
+            _ => return Err("Not a replay file".to_string()),
+        }
+
+        let mut replay = Replay::default();
+        let mut level_line = None;
+        for (n, line) in lines.by_ref() {
+            let err = |e: String| format!("line {n}: {e}");
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => {}
+                Some("seed") => replay.seed = parse(words.next(), "seed").map_err(err)?,
+                Some("tick_rate") => {
+                    replay.tick_rate = parse(words.next(), "tick rate").map_err(err)?;
+                }
+                Some("aspect") => replay.aspect = parse(words.next(), "aspect").map_err(err)?,
+                Some("inputs") => {
+                    replay.runs.extend(parse_runs(words).map_err(err)?);
+                }
+                Some("level") => {
+                    level_line = Some(n);
+                    break;
+                }
+                Some(other) => return Err(err(format!("Unknown line \"{other}\""))),
+            }
+        }
+
+        let Some(level_line) = level_line else {
+            return Err("Missing the `level` line".to_string());
+        };
+        let level_text: Vec<_> = lines.map(|(_, line)| line).collect();
+        replay.level = Level::parse(&level_text.join("\n"))
+            .map_err(|e| format!("In the level after line {level_line}: {e}"))?;
+
+        if !replay.tick_rate.is_finite() || replay.tick_rate <= 0. {
+            return Err(format!("Bad tick rate {}", replay.tick_rate));
+        }
+        if !replay.aspect.is_finite() || replay.aspect <= 0. {
+            return Err(format!("Bad aspect {}", replay.aspect));
+        }
+
+        Ok(replay)
+    }
+
+    pub fn to_text(&self) -> String {
+        use std::fmt::Write;
+
+        let mut text = String::new();
+        writeln!(text, "breakout-replay {REPLAY_VERSION}").unwrap();
+        writeln!(text, "seed {}", self.seed).unwrap();
+        writeln!(text, "tick_rate {}", self.tick_rate).unwrap();
+        writeln!(text, "aspect {}", self.aspect).unwrap();
+
+        // Keep the lines short enough to read
+        for runs in self.runs.chunks(16) {
+            let runs: Vec<_> = runs.iter().map(format_run).collect();
+            writeln!(text, "inputs {}", runs.join(" ")).unwrap();
+        }
+
+        writeln!(text, "level").unwrap();
+        text.push_str(&self.level.to_text());
+
+        text
+    }
+}
+
+/// Parse `<keys>*<ticks>` runs, like the ones after `inputs`
+pub fn parse_runs<'a>(words: impl IntoIterator<Item = &'a str>) -> Result<Vec<Run>, String> {
+    let mut runs = vec![];
+
+    for word in words {
+        let (keys, ticks) = match word.split_once('*') {
+            Some((keys, ticks)) => (keys, parse(Some(ticks), "tick count")?),
+            None => (word, 1),
+        };
+
+        let mut run = Run {
+            ticks,
+            ..Default::default()
+        };
+        for key in keys.chars() {
+            match key {
+                'L' | 'l' => run.input.left = true,
+                'R' | 'r' => run.input.right = true,
+                'B' | 'b' => run.input.spawn_ball = true,
+                'C' | 'c' => run.input.clear_balls = true,
+                'S' | 's' => run.input.launch = true,
+                'P' | 'p' => run.paused = true,
+                '.' => {}
+                _ => return Err(format!("Unknown key '{key}' in \"{word}\"")),
+            }
+        }
+
+        if run.ticks != 0 {
+            runs.push(run);
+        }
+    }
+
+    Ok(runs)
+}
+
+fn format_run(run: &Run) -> String {
+    let keys = [
+        (run.input.left, 'L'),
+        (run.input.right, 'R'),
+        (run.input.spawn_ball, 'B'),
+        (run.input.clear_balls, 'C'),
+        (run.input.launch, 'S'),
+        (run.paused, 'P'),
+    ];
+    let mut text: String = keys.iter().filter(|(on, _)| *on).map(|(_, c)| c).collect();
+    if text.is_empty() {
+        text.push('.');
+    }
+
+    if run.ticks != 1 {
+        text += &format!("*{}", run.ticks);
+    }
+
+    text
+}
+
+fn parse<T>(word: Option<&str>, what: &str) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let word = word.ok_or_else(|| format!("Missing {what}"))?;
+    word.parse()
+        .map_err(|e| format!("Bad {what} \"{word}\": {e}"))
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::game::GameState;
+    use crate::rng::Pcg32;
+    use pretty_assertions::assert_eq;
+    use rand::prelude::*;
+
+    const TICK_RATE: f32 = 200.;
+
+    /// Play some random inputs, recording them as we go
+    fn record_random_game(seed: u64, ticks: u32) -> (Replay, Game) {
+        let mut replay = Replay::new(Level::default_level(), 1.5, seed, TICK_RATE);
+        let mut game = replay.game();
+        let mut rng = Pcg32::new(seed ^ 1);
+
+        for _ in 0..ticks {
+            let input = FrameInput {
+                left: rng.random_bool(0.3),
+                right: rng.random_bool(0.3),
+                spawn_ball: rng.random_bool(0.01),
+                launch: game.state != GameState::Playing,
+                ..Default::default()
+            };
+            let paused = rng.random_bool(0.05);
+
+            replay.record(&input, paused);
+            if !paused {
+                game = game.step(&input, 1. / TICK_RATE);
+            }
+        }
+
+        (replay, game)
+    }
+
+    #[test]
+    fn check_playback_matches_the_game() {
+        let (replay, game) = record_random_game(0xb0a7, 3_000);
+        assert_eq!(replay.inputs().count(), 3_000);
+
+        let played = replay.play();
+        assert!(played == game, "The replay played out differently");
+    }
+
+    #[test]
+    fn check_text_round_trip() {
+        let (replay, _) = record_random_game(17, 500);
+
+        let text = replay.to_text();
+        assert!(text.starts_with(&format!("breakout-replay {REPLAY_VERSION}\n")));
+        assert_eq!(Replay::parse(&text).unwrap(), replay);
+    }
+
+    #[test]
+    fn check_runs() {
+        let runs = parse_runs("S L*40 .*3 RB P*2".split_whitespace()).unwrap();
+        let text: Vec<_> = runs.iter().map(format_run).collect();
+        assert_eq!(text.join(" "), "S L*40 .*3 RB P*2");
+
+        let mut replay = Replay::default();
+        for (input, paused) in runs
+            .iter()
+            .flat_map(|run| std::iter::repeat_n((run.input, run.paused), run.ticks as usize))
+        {
+            replay.record(&input, paused);
+        }
+        assert_eq!(replay.runs, runs);
+        assert_eq!(replay.played_ticks(), 45);
+
+        replay.truncate(41);
+        assert_eq!(replay.runs, parse_runs(["S", "L*40"]).unwrap());
+        replay.truncate(0);
+        assert_eq!(replay.runs, []);
+
+        assert!(parse_runs(["L*x"]).is_err());
+        assert!(parse_runs(["Q"]).is_err());
+    }
+
+    #[test]
+    fn check_bad_replays() {
+        let (replay, _) = record_random_game(3, 10);
+        let text = replay.to_text();
+
+        let newer = text.replace(
+            &format!("breakout-replay {REPLAY_VERSION}"),
+            "breakout-replay 999",
+        );
+        assert!(Replay::parse(&newer).unwrap_err().contains("version 999"));
+        assert!(Replay::parse("seed 1\n").is_err());
+
+        let no_level = text.split("level\n").next().unwrap();
+        assert!(Replay::parse(no_level).unwrap_err().contains("level"));
+
+        let aspect = format!("aspect {}\n", replay.aspect);
+        for bad in ["0", "-1.5", "inf", "NaN"] {
+            let text = text.replace(&aspect, &format!("aspect {bad}\n"));
+            assert!(
+                Replay::parse(&text).unwrap_err().contains("aspect"),
+                "{bad}"
+            );
+        }
+    }
+}
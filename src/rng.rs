@@ -0,0 +1,86 @@
+use rand::rand_core::{impls, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Small, seedable random number generator for the simulation.
+///
+/// This is PCG32 (XSH RR), from <https://www.pcg-random.org/>. We use our own instead of one of `rand`'s so
+/// that its output can never change out from under us, and its whole state is plain data we can save.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Pcg32 {
+    pub state: u64,
+    pub inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    pub fn new(seed: u64) -> Self {
+        // Any odd increment works, this is the one from the reference implementation
+        Self::new_with_stream(seed, 0xda3e39cb94b95bdb)
+    }
+
+    /// Seed a generator on its own stream. Generators on different streams never produce the same sequence.
+    pub fn new_with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.inc);
+    }
+}
+
+impl RngCore for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dst)
+    }
+}
+
+/// Pick a seed for when nobody asked for a specific one
+pub fn random_seed() -> u64 {
+    use rand::prelude::*;
+
+    rand::rng().random()
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn check_reference_output() {
+        // First outputs of the reference implementation's demo, seeded with (42, 54)
+        let mut rng = Pcg32::new_with_stream(42, 54);
+        let expected = [
+            0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e,
+        ];
+
+        for e in expected {
+            assert_eq!(rng.next_u32(), e);
+        }
+    }
+}
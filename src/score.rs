@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::world::Event;
+
+/// Bricks broken in a row (without touching the paddle) for each step up in the multiplier
+pub const COMBO_STEP: u32 = 5;
+
+/// The combo multiplier never goes past this
+pub const MAX_MULTIPLIER: u32 = 5;
+
+/// Clearing a level faster than this many seconds earns a bonus
+pub const PAR_TIME: f32 = 120.;
+
+/// Bonus for each second under `PAR_TIME`
+pub const BONUS_PER_SECOND: u64 = 50;
+
+/// The player's score, built up from what happens in the world.
+///
+/// Feed it every step's events with [`Score::record()`], and tell it when a level is cleared with
+/// [`Score::level_cleared()`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub points: u64,
+
+    /// Bricks broken since a ball last touched the paddle
+    pub combo: u32,
+
+    /// Longest combo this game
+    pub best_combo: u32,
+
+    /// Seconds spent playing the current level
+    pub level_time: f32,
+
+    /// Bonus from the last level we cleared
+    pub last_bonus: u64,
+}
+
+impl Score {
+    /// What the next brick is multiplied by
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.combo / COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+
+    /// Score everything that happened during a step. With more than one player, only pass in the events that
+    /// are theirs.
+    pub fn record<'a>(&mut self, events: impl IntoIterator<Item = &'a Event>) {
+        for event in events {
+            match event {
+                Event::BrickBroken { brick, .. } => {
+                    self.points += brick.points as u64 * self.multiplier() as u64;
+                    self.combo += 1;
+                    self.best_combo = self.best_combo.max(self.combo);
+                }
+
+                // Combos only last as long as the ball stays away from the paddle
+                Event::PaddleHit { .. } | Event::BallLost { .. } => self.combo = 0,
+
+                Event::BrickDamaged { .. } | Event::PowerUpCollected { .. } => {}
+            }
+        }
+    }
+
+    /// Add the bonus for clearing a level in `level_time`, and start timing the next one
+    pub fn level_cleared(&mut self) {
+        let seconds_under_par = (PAR_TIME - self.level_time).max(0.) as u64;
+
+        self.last_bonus = seconds_under_par * BONUS_PER_SECOND;
+        self.points += self.last_bonus;
+        self.level_time = 0.;
+        self.combo = 0;
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::world::{Ball, Brick, Quad};
+    use pretty_assertions::assert_eq;
+
+    fn broken(points: u32) -> Event {
+        Event::BrickBroken {
+            brick: Brick {
+                points,
+                ..Brick::new(Quad::default(), 1)
+            },
+            player: 0,
+        }
+    }
+
+    #[test]
+    fn check_combos_multiply_points() {
+        let mut score = Score::default();
+
+        // The first few bricks are worth what they say
+        score.record(&[broken(7), broken(5)]);
+        assert_eq!(score.points, 12);
+        assert_eq!(score.combo, 2);
+
+        // Then they start to double up
+        score.record(&[broken(1), broken(1), broken(1)]);
+        assert_eq!(score.points, 15);
+        assert_eq!(score.multiplier(), 2);
+        score.record(&[broken(3)]);
+        assert_eq!(score.points, 21);
+
+        // Touching the paddle starts over
+        score.record(&[Event::PaddleHit {
+            ball: Ball::default(),
+        }]);
+        assert_eq!(score.combo, 0);
+        assert_eq!(score.best_combo, 6);
+        score.record(&[broken(3)]);
+        assert_eq!(score.points, 24);
+    }
+
+    #[test]
+    fn check_multiplier_is_capped() {
+        let mut score = Score {
+            combo: 1_000,
+            ..Default::default()
+        };
+        assert_eq!(score.multiplier(), MAX_MULTIPLIER);
+
+        score.record(&[broken(2)]);
+        assert_eq!(score.points, 2 * MAX_MULTIPLIER as u64);
+    }
+
+    #[test]
+    fn check_quick_clears_earn_a_bonus() {
+        let mut score = Score {
+            points: 100,
+            level_time: PAR_TIME - 10.,
+            ..Default::default()
+        };
+        score.level_cleared();
+        assert_eq!(score.last_bonus, 10 * BONUS_PER_SECOND);
+        assert_eq!(score.points, 100 + 10 * BONUS_PER_SECOND);
+        assert_eq!(score.level_time, 0.);
+
+        // Slow clears don't lose anything
+        score.level_time = 2. * PAR_TIME;
+        score.level_cleared();
+        assert_eq!(score.last_bonus, 0);
+        assert_eq!(score.points, 100 + 10 * BONUS_PER_SECOND);
+    }
+}
@@ -0,0 +1,213 @@
+//! Whole games saved to a file, so we can come back to a tricky spot later.
+//!
+//! Snapshots are JSON, with a version number in front of everything else:
+//!
+//! ```text
+//! { "version": 4, "game": { "world": { "balls": [ ... ], ... }, "state": "Playing", "players": [ ... ], ... } }
+//! ```
+//!
+//! Everything that decides what happens next is in there, including the rng's state, so a loaded game
+//! carries on exactly the way the saved one would have.
+
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+use crate::game::Game;
+
+/// Bumped whenever old snapshots stop loading into the current structs
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    game: &'a Game,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    game: Game,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+pub fn to_json(game: &Game) -> String {
+    let snapshot = SnapshotRef {
+        version: SNAPSHOT_VERSION,
+        game,
+    };
+
+    serde_json::to_string(&snapshot).expect("Games should always serialize")
+}
+
+pub fn from_json(text: &str) -> Result<Game, String> {
+    // Check the version before anything else, so old snapshots get a better error than a missing field
+    let Version { version } =
+        serde_json::from_str(text).map_err(|e| format!("Not a snapshot: {e}"))?;
+    if version != SNAPSHOT_VERSION {
+        return Err(format!(
+            "Snapshot version {version} isn't supported (expected {SNAPSHOT_VERSION})"
+        ));
+    }
+
+    let Snapshot { mut game } =
+        serde_json::from_str(text).map_err(|e| format!("Bad snapshot: {e}"))?;
+    check_players(&game).map_err(|e| format!("Bad snapshot: {e}"))?;
+    game.world.rebuild_brick_grid();
+
+    Ok(game)
+}
+
+/// Make sure everything that points at a player or a paddle has one to point at, so the game doesn't fall
+/// over the first time it's stepped
+fn check_players(game: &Game) -> Result<(), String> {
+    let world = &game.world;
+    let paddles = world.paddles.len();
+    if paddles != game.mode.players() || game.players.len() != paddles {
+        return Err(format!(
+            "{:?} has {} players, but there are {} players and {paddles} paddles",
+            game.mode,
+            game.mode.players(),
+            game.players.len()
+        ));
+    }
+
+    let check = |what: &str, index: usize| {
+        if index < paddles {
+            Ok(())
+        } else {
+            Err(format!("{what} {index} doesn't exist"))
+        }
+    };
+    check("The serving player", game.serving)?;
+    for ball in &world.balls {
+        check("Ball's player", ball.player)?;
+        if let Some(stuck) = ball.stuck {
+            check("Ball's paddle", stuck.paddle)?;
+        }
+    }
+    for laser in &world.lasers {
+        check("Laser's player", laser.player)?;
+    }
+
+    Ok(())
+}
+
+pub fn save(game: &Game, path: impl AsRef<Path>) -> Result<(), String> {
+    let path = path.as_ref();
+    std::fs::write(path, to_json(game))
+        .map_err(|e| format!("Couldn't write {}: {e}", path.display()))
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Game, String> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+
+    from_json(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// JSON doesn't have infinity, so we write it as `null` instead
+pub(crate) mod maybe_infinite {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        value.is_finite().then_some(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        Ok(Option::<f32>::deserialize(deserializer)?.unwrap_or(f32::INFINITY))
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use crate::game::GameState;
+    use crate::powerup::PowerUpKind;
+    use crate::world::{FrameInput, StuckToPaddle};
+    use pretty_assertions::assert_eq;
+
+    const DT: f32 = 5e-3;
+
+    const LAUNCH: FrameInput = FrameInput {
+        left: false,
+        right: true,
+        spawn_ball: false,
+        clear_balls: false,
+        launch: true,
+    };
+
+    /// Somewhere in the middle of a game, with a bit of everything going on
+    fn busy_game() -> Game {
+        let mut game = Game::new(1.5, 0xb0a7);
+        for _ in 0..500 {
+            game = game.step(&LAUNCH, DT);
+        }
+        game.world.apply_power_up(PowerUpKind::MultiBall);
+        game.world.effects.start(PowerUpKind::Laser, 5.);
+        game.world.serve_ball(0);
+        game
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let game = busy_game();
+        assert_eq!(game.state, GameState::Playing);
+
+        let loaded = from_json(&to_json(&game)).unwrap();
+        assert!(loaded == game, "The loaded game is different");
+
+        // And it keeps going the same way
+        let (mut a, mut b) = (game, loaded);
+        for _ in 0..1_000 {
+            a = a.step(&LAUNCH, DT);
+            b = b.step(&LAUNCH, DT);
+        }
+        assert!(a == b, "The loaded game played out differently");
+        assert_eq!(a.players, b.players);
+    }
+
+    #[test]
+    fn check_versions() {
+        let json = to_json(&busy_game());
+        assert!(json.starts_with(&format!("{{\"version\":{SNAPSHOT_VERSION},")));
+
+        let newer = json.replacen(
+            &format!("\"version\":{SNAPSHOT_VERSION}"),
+            "\"version\":999",
+            1,
+        );
+        let Err(e) = from_json(&newer) else {
+            panic!("Loaded a snapshot from the future");
+        };
+        assert!(e.contains("version 999"), "{e}");
+
+        assert!(from_json("{}").is_err());
+        assert!(from_json(&format!("{{\"version\":{SNAPSHOT_VERSION}}}")).is_err());
+    }
+
+    #[test]
+    fn check_bad_paddles() {
+        let mut game = busy_game();
+        game.world.balls[0].stuck = Some(StuckToPaddle {
+            paddle: 1,
+            ..Default::default()
+        });
+        let Err(e) = from_json(&to_json(&game)) else {
+            panic!("Loaded a ball stuck to a missing paddle");
+        };
+        assert!(e.contains("paddle 1"), "{e}");
+
+        let mut game = busy_game();
+        game.serving = 3;
+        assert!(from_json(&to_json(&game)).is_err());
+
+        let mut game = busy_game();
+        game.players.push(game.players[0]);
+        assert!(from_json(&to_json(&game)).is_err());
+    }
+}
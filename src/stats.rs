@@ -0,0 +1,275 @@
+//! Numbers from games the AI plays start to finish, for balancing levels and catching physics changes.
+//!
+//! ```
+//! use breakout::game::Game;
+//! use breakout::stats::{Autoplay, Distribution};
+//!
+//! let autoplay = Autoplay::default();
+//! let games: Vec<_> = (0..4).map(|seed| autoplay.play(Game::new(1.5, seed), seed)).collect();
+//! let balls_lost = Distribution::of(games.iter().map(|stats| stats.balls_lost as f32));
+//! ```
+
+use serde::Serialize;
+
+use std::collections::HashMap;
+
+use crate::controller::{Ai, PaddleController, Skill};
+use crate::game::{Game, GameState};
+use crate::timestep::DEFAULT_TICK_RATE;
+use crate::world::{Event, FrameInput};
+
+/// How a game played by [`Autoplay`] ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Outcome {
+    /// Every brick is gone
+    Cleared,
+
+    /// Someone ran out of lives
+    GameOver,
+
+    /// Still going when time ran out
+    TimedOut,
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Cleared => "cleared",
+            Outcome::GameOver => "game-over",
+            Outcome::TimedOut => "timed-out",
+        }
+    }
+}
+
+/// What happened in one game
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct GameStats {
+    pub outcome: Outcome,
+
+    /// How long it went on for, in game time
+    pub seconds: f32,
+
+    pub balls_lost: u32,
+    pub bricks_broken: u32,
+
+    /// Balls that went [`Autoplay::stuck_seconds`] without coming back to a paddle. Each ball only counts
+    /// once until it does.
+    pub stuck_balls: u32,
+
+    /// Everyone's points added up
+    pub points: u64,
+}
+
+impl GameStats {
+    /// Seconds it took to clear the level, if it got cleared
+    pub fn completion_time(&self) -> Option<f32> {
+        (self.outcome == Outcome::Cleared).then_some(self.seconds)
+    }
+
+    pub fn bricks_per_minute(&self) -> f32 {
+        if self.seconds > 0. {
+            self.bricks_broken as f32 * 60. / self.seconds
+        } else {
+            0.
+        }
+    }
+}
+
+/// Plays games with an [`Ai`] on every paddle, as fast as it can, and keeps track of how they go
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Autoplay {
+    pub skill: Skill,
+    pub tick_rate: f32,
+
+    /// Games still going after this many seconds are stopped
+    pub max_seconds: f32,
+
+    /// Seconds a ball can go without touching a paddle before it counts as stuck, like when it's caught
+    /// bouncing between walls
+    pub stuck_seconds: f32,
+}
+
+impl Default for Autoplay {
+    fn default() -> Self {
+        Self {
+            skill: Skill::default(),
+            tick_rate: DEFAULT_TICK_RATE,
+            max_seconds: 600.,
+            stuck_seconds: 30.,
+        }
+    }
+}
+
+impl Autoplay {
+    /// Play `game` until the level is cleared, the game is over, or time runs out. `seed` picks how the AIs'
+    /// guesses are off, and the same game and seed always play out the same way.
+    pub fn play(&self, mut game: Game, seed: u64) -> GameStats {
+        let dt = 1. / self.tick_rate;
+        let max_ticks = (self.max_seconds * self.tick_rate).ceil() as u64;
+        let stuck_ticks = (self.stuck_seconds * self.tick_rate).ceil() as u64;
+
+        let mut ais: Vec<_> = (0..game.players.len())
+            .map(|player| Ai::new(self.skill, self.tick_rate, seed.wrapping_add(player as u64)))
+            .collect();
+        let mut inputs = vec![FrameInput::default(); ais.len()];
+        let mut next = Game::default();
+
+        let mut stats = GameStats {
+            outcome: Outcome::TimedOut,
+            seconds: 0.,
+            balls_lost: 0,
+            bricks_broken: 0,
+            stuck_balls: 0,
+            points: 0,
+        };
+
+        // Ticks each ball in play has gone without a paddle, by id. Counted ones are already in `stuck_balls`.
+        let mut since_paddle: HashMap<u32, (u64, bool)> = HashMap::new();
+
+        let mut ticks = 0;
+        while ticks < max_ticks {
+            for (player, ai) in ais.iter_mut().enumerate() {
+                inputs[player] = ai.next_input(&game.world, player);
+            }
+            game.step_players_into(&inputs, dt, &mut next);
+            std::mem::swap(&mut game, &mut next);
+            ticks += 1;
+
+            for event in &game.world.events {
+                match event {
+                    Event::BallLost { .. } => stats.balls_lost += 1,
+                    Event::BrickBroken { .. } => stats.bricks_broken += 1,
+                    _ => {}
+                }
+            }
+
+            since_paddle.retain(|id, _| game.world.balls.iter().any(|ball| ball.id == *id));
+            for ball in &game.world.balls {
+                let hit_paddle = game.world.events.iter().any(
+                    |event| matches!(event, Event::PaddleHit { ball: hit } if hit.id == ball.id),
+                );
+                let (ticks, counted) = since_paddle.entry(ball.id).or_default();
+                if hit_paddle || ball.stuck.is_some() {
+                    (*ticks, *counted) = (0, false);
+                    continue;
+                }
+
+                *ticks += 1;
+                if *ticks >= stuck_ticks && !*counted {
+                    *counted = true;
+                    stats.stuck_balls += 1;
+                }
+            }
+
+            match game.state {
+                GameState::LevelCleared => {
+                    stats.outcome = Outcome::Cleared;
+                    break;
+                }
+                GameState::GameOver => {
+                    stats.outcome = Outcome::GameOver;
+                    break;
+                }
+                GameState::Serve | GameState::Playing => {}
+            }
+        }
+
+        stats.seconds = ticks as f32 * dt;
+        stats.points = game.players.iter().map(|player| player.score.points).sum();
+        stats
+    }
+}
+
+/// The spread of some number over a bunch of games
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f32,
+    pub min: f32,
+    pub p10: f32,
+    pub median: f32,
+    pub p90: f32,
+    pub max: f32,
+}
+
+impl Distribution {
+    /// `None` if there's nothing to go on. Percentiles are whichever value is closest to that rank.
+    pub fn of(values: impl IntoIterator<Item = f32>) -> Option<Self> {
+        let mut values: Vec<_> = values.into_iter().collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f32::total_cmp);
+
+        let percentile = |p: f32| values[(p * (values.len() - 1) as f32).round() as usize];
+        Some(Self {
+            count: values.len(),
+            mean: values.iter().sum::<f32>() / values.len() as f32,
+            min: values[0],
+            p10: percentile(0.1),
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            max: values[values.len() - 1],
+        })
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use ultraviolet::Vec2;
+
+    #[test]
+    fn check_distribution() {
+        let d = Distribution::of((1..=11).rev().map(|x| x as f32)).unwrap();
+        assert_eq!(d.count, 11);
+        assert_eq!(
+            (d.min, d.p10, d.median, d.p90, d.max),
+            (1., 2., 6., 10., 11.)
+        );
+        assert_eq!(d.mean, 6.);
+
+        assert_eq!(Distribution::of([]), None);
+    }
+
+    #[test]
+    fn check_same_seed_same_stats() {
+        let autoplay = Autoplay {
+            max_seconds: 20.,
+            ..Default::default()
+        };
+        let a = autoplay.play(Game::new(1.5, 3), 3);
+        let b = autoplay.play(Game::new(1.5, 3), 3);
+        assert_eq!(a, b);
+
+        assert!(a.bricks_broken > 0, "{a:?}");
+        assert!(a.seconds <= 20.);
+        if a.outcome == Outcome::TimedOut {
+            assert_eq!(a.completion_time(), None);
+        }
+    }
+
+    #[test]
+    fn check_stuck_balls() {
+        // Sideways between the walls forever, just over the paddle, with nothing to steer it up or down
+        let mut game = Game::new(1.5, 0);
+        game.world.rules.speed.min_vertical = 0.;
+        game.state = GameState::Playing;
+        let paddle = game.world.paddles[0];
+        let ball = &mut game.world.balls[0];
+        ball.stuck = None;
+        ball.pos = Vec2::new(10., paddle.pos.y + paddle.dims.y + 5.);
+        ball.vel = Vec2::new(100., 0.);
+
+        let autoplay = Autoplay {
+            max_seconds: 10.,
+            stuck_seconds: 2.,
+            ..Default::default()
+        };
+        let stats = autoplay.play(game, 0);
+        assert_eq!(stats.stuck_balls, 1);
+        assert_eq!(stats.outcome, Outcome::TimedOut);
+        assert_eq!(stats.balls_lost, 0);
+    }
+}
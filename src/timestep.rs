@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+/// Ticks per second when nothing else is asked for
+pub const DEFAULT_TICK_RATE: f32 = 200.;
+
+/// How many ticks we'll run in one frame to catch up before giving up on the lost time
+pub const DEFAULT_MAX_CATCHUP_STEPS: u32 = 10;
+
+/// Turns a wall-clock into a steady stream of fixed-size simulation ticks.
+///
+/// Every frame, call [`FixedTimestep::advance()`] with the current time, run that many ticks, then render
+/// using [`FixedTimestep::alpha()`] to blend between the last two ticks.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    tick: Duration,
+    max_steps: u32,
+
+    /// Time we haven't simulated yet. This is always less than one tick between calls to `advance()`.
+    accumulator: Duration,
+    last: Option<Instant>,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f32, max_steps: u32) -> Self {
+        assert!(
+            tick_rate > 0.,
+            "Tick rate must be positive, but it's {tick_rate}"
+        );
+        assert!(max_steps > 0, "max_steps must allow at least one step");
+
+        Self {
+            tick: Duration::from_secs_f64(1. / tick_rate as f64),
+            max_steps,
+            accumulator: Duration::ZERO,
+            last: None,
+        }
+    }
+
+    /// Seconds per tick
+    pub fn dt(&self) -> f32 {
+        self.tick.as_secs_f32()
+    }
+
+    /// Account for the time passed since the last call and return how many ticks to simulate now.
+    ///
+    /// The first call only starts the clock and never asks for any ticks.
+    pub fn advance(&mut self, now: Instant) -> u32 {
+        let Some(last) = self.last.replace(now) else {
+            return 0;
+        };
+        self.accumulator += now.saturating_duration_since(last);
+
+        let mut steps = 0;
+        while self.accumulator >= self.tick {
+            self.accumulator -= self.tick;
+            steps += 1;
+
+            if steps == self.max_steps {
+                // We've fallen too far behind (or were stopped in a debugger), so drop the backlog
+                // instead of trying to run it all at once.
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+        }
+
+        steps
+    }
+
+    /// How far we are between the last tick and the next one, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f32() / self.tick.as_secs_f32()).clamp(0., 1.)
+    }
+}
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn check_steps_from_elapsed_time() {
+        let start = Instant::now();
+        let mut timestep = FixedTimestep::new(100., 10);
+
+        assert_eq!(timestep.advance(start), 0);
+
+        // 25ms is 2.5 ticks
+        assert_eq!(timestep.advance(start + Duration::from_millis(25)), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-3);
+
+        // The leftover half tick carries over
+        assert_eq!(timestep.advance(start + Duration::from_millis(30)), 1);
+        assert!(timestep.alpha() < 1e-3);
+    }
+
+    #[test]
+    fn check_catchup_is_capped() {
+        let start = Instant::now();
+        let mut timestep = FixedTimestep::new(100., 10);
+
+        timestep.advance(start);
+        assert_eq!(timestep.advance(start + Duration::from_secs(5)), 10);
+        assert_eq!(timestep.alpha(), 0.);
+
+        // The backlog is gone, so we're back to normal
+        assert_eq!(
+            timestep.advance(start + Duration::from_secs(5) + Duration::from_millis(10)),
+            1
+        );
+    }
+}
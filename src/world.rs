@@ -1,14 +1,58 @@
+use serde::{Deserialize, Serialize};
 use ultraviolet::{Vec2, Vec3};
 
-#[derive(Clone, Default)]
+use crate::color;
+use crate::level::{Cell, Level};
+use crate::math::{Aabb, HitInfo, UniformGrid};
+use crate::powerup::{Capsule, DropTable, Effects, PowerUpKind, PowerUpRules};
+use crate::rng::Pcg32;
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct World {
     pub balls: Vec<Ball>,
-    pub bricks: Vec<Quad>,
+    pub bricks: Vec<Brick>,
     pub unbreakable_bricks: Vec<Quad>,
-    pub paddle: Quad,
+
+    /// One for each player, in player order. Paddles in the bottom half of the playfield send balls up, and
+    /// paddles in the top half send them down.
+    pub paddles: Vec<Quad>,
+
+    /// Power-ups falling towards the paddles
+    pub capsules: Vec<Capsule>,
+
+    /// Laser shots heading away from the paddles
+    pub lasers: Vec<Laser>,
+
+    /// Timers for the power-ups the player has
+    pub effects: Effects,
+
+    /// Size of the playfield. Balls leaving `(0, 0)..bounds` are dropped.
+    pub bounds: Vec2,
+
+    /// Size of every brick on the board, which `brick_grid`'s cells are sized to fit
+    pub brick_dims: Vec2,
+    pub rules: Rules,
+
+    /// Everything notable that happened during the step that produced this world
+    pub events: Vec<Event>,
+
+    /// Id for the next ball we create
+    pub next_ball_id: u32,
+
+    /// Broadphase for `bricks` followed by `unbreakable_bricks`. Ids past the end of `bricks` are unbreakable.
+    ///
+    /// Stepping keeps this up to date. If you change the bricks some other way, call
+    /// [`World::rebuild_brick_grid()`] afterwards. This isn't saved in snapshots, since it's all derived from
+    /// the bricks.
+    #[serde(skip)]
+    pub brick_grid: UniformGrid,
+
+    /// Every random choice the simulation makes comes from here, so the same seed and inputs always play out
+    /// exactly the same way.
+    pub rng: Pcg32,
 }
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Quad {
     pub pos: Vec2,
     pub vel: Vec2,
@@ -16,37 +60,1867 @@ pub struct Quad {
     pub color: Vec3,
 }
 
-#[derive(Copy, Clone, Debug, Default)]
+/// A brick that breaks after taking `max_hp` hits.
+///
+/// `quad.color` is its color at full health, and it fades towards gray as it takes damage. Draw it with
+/// [`Brick::color()`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Brick {
+    pub quad: Quad,
+
+    /// Hits left before it breaks. Bricks in a world always have at least one.
+    pub hp: u32,
+    pub max_hp: u32,
+
+    /// What this brick can drop when it breaks
+    pub drops: DropTable,
+
+    /// Score for breaking this brick, before any multipliers
+    pub points: u32,
+}
+
+impl Brick {
+    /// A brick at full health
+    pub fn new(quad: Quad, max_hp: u32) -> Self {
+        assert!(max_hp > 0, "Bricks need at least one hit point");
+
+        Self {
+            quad,
+            hp: max_hp,
+            max_hp,
+            drops: DropTable::NONE,
+            points: 1,
+        }
+    }
+
+    /// Color to draw this brick with, faded towards gray by how damaged it is
+    pub fn color(&self) -> Vec3 {
+        let damage = 1. - self.hp as f32 / self.max_hp.max(1) as f32;
+
+        // Don't go all the way to gray, so you can still tell what a brick used to be
+        let t = 0.75 * damage;
+        self.quad.color + t * (color::GRAY - self.quad.color)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Ball {
+    /// Unique for each ball in a world, and stays the same from step to step
+    pub id: u32,
     pub pos: Vec2,
     pub vel: Vec2,
     pub radius: f32,
+
+    /// Player whose paddle this ball last bounced off of, or was served from
+    pub player: usize,
+
+    /// Set while the ball is held on top of the paddle, waiting to be served or caught by a sticky paddle.
+    ///
+    /// It keeps the speed in `vel` when it lets go, but it's aimed by where it sits on the paddle, the same as
+    /// if it had just bounced off of it there.
+    pub stuck: Option<StuckToPaddle>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StuckToPaddle {
+    /// Which of `World::paddles` is holding it
+    pub paddle: usize,
+
+    /// How far right of the middle of the paddle the ball is stuck
+    pub offset: f32,
+
+    /// Seconds until it lets go. Infinite for balls that wait to be launched.
+    #[serde(with = "crate::snapshot::maybe_infinite")]
+    pub release_in: f32,
+}
+
+/// Things that happen during a step, for anything watching the simulation from the outside
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    /// A ball or laser from `player` hit this brick, and it has hit points left
+    BrickDamaged { brick: Brick, player: usize },
+
+    /// A ball or laser from `player` hit this brick on its last hit point and it was removed
+    BrickBroken { brick: Brick, player: usize },
+
+    /// This ball left the playfield and was removed
+    BallLost { ball: Ball },
+
+    /// This ball bounced off the front of a paddle. `ball.player` is whose paddle it was.
+    PaddleHit { ball: Ball },
+
+    /// The paddle caught a capsule and the player got this power-up
+    PowerUpCollected { kind: PowerUpKind },
+}
+
+/// A laser shot, and the player whose paddle fired it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Laser {
+    pub quad: Quad,
+    pub player: usize,
+}
+
+/// Who's playing, and where their paddles go
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    /// One paddle at the bottom
+    #[default]
+    Single,
+
+    /// Two paddles side by side at the bottom, each with its own half of the playfield
+    CoOp,
+
+    /// One paddle at the bottom and one at the top, with the bricks in between and no wall behind either
+    Versus,
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "single" => Some(Mode::Single),
+            "co-op" => Some(Mode::CoOp),
+            "versus" => Some(Mode::Versus),
+            _ => None,
+        }
+    }
+
+    pub fn players(self) -> usize {
+        match self {
+            Mode::Single => 1,
+            Mode::CoOp | Mode::Versus => 2,
+        }
+    }
 }
 
+/// Tunables for the simulation
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    /// How fast the paddle slides left and right, in units per second
+    pub paddle_speed: f32,
+
+    /// Speed of newly spawned balls, in units per second
+    pub ball_speed: f32,
+
+    pub ball_radius: f32,
+
+    /// Width of the paddle without any power-ups
+    pub paddle_width: f32,
+
+    /// Widest angle from straight up that the paddle sends balls off at, in radians.
+    /// Balls that land on the paddle's edges leave at this angle, and balls in the middle go straight up.
+    pub paddle_bounce_cone: f32,
+
+    /// How much of the paddle's sideways velocity is added to balls bouncing off of it
+    pub paddle_vel_influence: f32,
+
+    /// Balls bounce off of each other. Turn this off to let them pass through each other instead.
+    pub ball_collisions: bool,
+
+    pub power_ups: PowerUpRules,
+
+    pub speed: SpeedRules,
+}
+
+/// How ball speeds change while they're in play. Speeds here are multiples of `Rules::ball_speed`, so they
+/// work the same for slow and fast levels.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeedRules {
+    /// Slowest a ball can go
+    pub min: f32,
+
+    /// Fastest a ball can go
+    pub max: f32,
+
+    /// How much faster balls get for every second they're in play
+    pub per_second: f32,
+
+    /// How much faster a ball gets each time it bounces off the paddle
+    pub per_paddle_hit: f32,
+
+    /// How much faster a ball gets each time it hits a brick in the back rows
+    pub per_back_row_hit: f32,
+
+    /// Bricks this close to the top of the playfield are in the back rows
+    pub back_row_depth: f32,
+
+    /// Smallest part of a ball's speed that has to be up or down, from 0 to 1. This stops balls from bouncing
+    /// back and forth between the side walls forever.
+    pub min_vertical: f32,
+}
+
+impl SpeedRules {
+    /// Balls keep whatever speed they're given
+    pub const FIXED: SpeedRules = SpeedRules {
+        min: 0.,
+        max: f32::MAX,
+        per_second: 0.,
+        per_paddle_hit: 0.,
+        per_back_row_hit: 0.,
+        back_row_depth: 0.,
+        min_vertical: 0.,
+    };
+}
+
+impl Default for SpeedRules {
+    fn default() -> Self {
+        Self {
+            min: 0.75,
+            max: 2.,
+            per_second: 0.01,
+            per_paddle_hit: 0.02,
+            per_back_row_hit: 0.05,
+            back_row_depth: 8.,
+            min_vertical: 0.2,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            paddle_speed: 400.,
+            ball_speed: 135.,
+            ball_radius: 0.5,
+            paddle_width: 4. * BRICK_DIMS.x,
+            // Same spread that `random_direction()` launches balls with
+            paddle_bounce_cone: 0.25 * std::f32::consts::PI,
+            paddle_vel_influence: 0.1,
+            ball_collisions: true,
+            power_ups: PowerUpRules::default(),
+            speed: SpeedRules::default(),
+        }
+    }
+}
+
+/// Everything a player can do in a single simulation step
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameInput {
+    /// Slide the paddle left
+    pub left: bool,
+
+    /// Slide the paddle right
+    pub right: bool,
+
+    /// Spawn a new ball in front of the paddle
+    pub spawn_ball: bool,
+
+    /// Remove every ball, whoever's they are
+    pub clear_balls: bool,
+
+    /// Let go of every ball stuck to the paddle
+    pub launch: bool,
+}
+
+/// Shape of a brick, unless a level says otherwise. The paddle is a few bricks wide.
+pub const BRICK_DIMS: Vec2 = Vec2::new(5., 1.);
+
 impl World {
+    /// Build the starting board from the default level. See [`World::from_level()`].
+    pub fn new(aspect: f32, seed: u64) -> Self {
+        Self::from_level(&Level::default_level(), aspect, seed)
+    }
+
+    /// Build the starting board for `level`, for a single player.
+    ///
+    /// The playfield is exactly as wide as the level, and `aspect` (height / width) picks how tall it is.
+    /// `seed` drives everything random that happens in the world.
+    pub fn from_level(level: &Level, aspect: f32, seed: u64) -> Self {
+        Self::from_level_with(level, aspect, seed, Mode::Single)
+    }
+
+    /// Build the starting board for `level`, with a paddle for each player in `mode`.
+    ///
+    /// In versus, the board is moved down to the middle of the playfield so both sides are the same distance
+    /// from it.
+    pub fn from_level_with(level: &Level, aspect: f32, seed: u64, mode: Mode) -> Self {
+        let mut world = World {
+            rng: Pcg32::new(seed),
+            ..Default::default()
+        };
+        world.rules.ball_speed = level.ball_speed;
+        world.rules.paddle_width = level.paddle_dims.x;
+
+        // Shape of a brick
+        let dims = level.brick_dims;
+        world.brick_dims = dims;
+
+        // for the board
+        let view_x = (dims.x + 1.) * (level.columns as f32) + 1.;
+        let view_y = view_x * aspect;
+        world.bounds = Vec2::new(view_x, view_y);
+
+        // Add some unbreakable bricks off screen on the top, left and right
+        {
+            // Side-walls don't move
+            let vel = Vec2::zero();
+            let color = crate::UNBREAKABLE_BRICK_COLOR;
+
+            // Swap the commented `pos` below to move the walls in-frame to see them
+
+            // Left
+            world.unbreakable_bricks.push(Quad {
+                // pos: Vec2::new(0., 0.),
+                pos: Vec2::new(0. - 1., 0.),
+                vel,
+                dims: Vec2::new(1., view_y),
+                color,
+            });
+
+            // Right
+            world.unbreakable_bricks.push(Quad {
+                // pos: Vec2::new(view_x - 1., 0.),
+                pos: Vec2::new(view_x, 0.),
+                vel,
+                dims: Vec2::new(1., view_y),
+                color,
+            });
+
+            // Top, unless there's a player up there
+            if mode != Mode::Versus {
+                world.unbreakable_bricks.push(Quad {
+                    // pos: Vec2::new(0., view_y - 1.),
+                    pos: Vec2::new(0., view_y),
+                    vel,
+                    dims: Vec2::new(view_x, 1.),
+                    color,
+                });
+            }
+        }
+
+        // Split the space under the board evenly above and below it
+        let board_drop = match mode {
+            Mode::Versus => (0.5 * (view_y - (dims.y + 1.) * level.rows as f32 - 1.)).max(0.),
+            Mode::Single | Mode::CoOp => 0.,
+        };
+
+        // (x, y) are position in the grid
+        for y in 0..level.rows {
+            for x in 0..level.columns {
+                // Note: Our x coordinate here must match the calculation for view_x above
+                let pos_x = (dims.x + 1.) * (x as f32) + 1.;
+                let pos_y = view_y - (dims.y + 1.) * (y as f32 + 1.) - board_drop;
+                let quad = |color| Quad {
+                    pos: Vec2::new(pos_x, pos_y),
+                    vel: Vec2::zero(),
+                    dims,
+                    color,
+                };
+
+                match level.cell(x, y) {
+                    Cell::Empty => {}
+                    Cell::Brick(kind) => world.bricks.push(Brick {
+                        drops: kind.drops,
+                        points: kind.points,
+                        ..Brick::new(quad(kind.color), kind.hp)
+                    }),
+                    Cell::Wall(color) => world.unbreakable_bricks.push(quad(color)),
+                }
+            }
+        }
+
+        // Add a user-controlled paddle for each player, starting in the middle of its lane
+        let paddle_dims = level.paddle_dims;
+        let bottom = 0.05 * view_y;
+        let top = 0.95 * view_y - paddle_dims.y;
+        let heights: &[f32] = match mode {
+            Mode::Single => &[bottom],
+            Mode::CoOp => &[bottom, bottom],
+            Mode::Versus => &[bottom, top],
+        };
+        for &y in heights {
+            world.paddles.push(Quad {
+                pos: Vec2::new(0., y),
+                vel: Vec2::zero(),
+                dims: paddle_dims,
+                color: color::WHITE,
+            });
+        }
+        for i in 0..world.paddles.len() {
+            let (left, right) = paddle_lane(&world.paddles, i, view_x);
+            world.paddles[i].pos.x = (0.5 * (left + right) - 0.5 * paddle_dims.x)
+                .clamp(left, (right - paddle_dims.x).max(left));
+        }
+
+        // Spawn a starter ball
+        world.create_ball(world.ball_spawn_pos(0));
+
+        world.rebuild_brick_grid();
+
+        world
+    }
+
+    /// Rebuild `brick_grid` from scratch
+    pub fn rebuild_brick_grid(&mut self) {
+        build_brick_grid(
+            &mut self.brick_grid,
+            self.bounds,
+            self.brick_dims,
+            &self.bricks,
+            &self.unbreakable_bricks,
+        );
+    }
+
     pub fn reset(&mut self) {
         self.balls.clear();
         self.bricks.clear();
         self.unbreakable_bricks.clear();
-        self.paddle = Default::default();
+        self.paddles.clear();
+        self.capsules.clear();
+        self.lasers.clear();
+        self.effects = Default::default();
+        self.events.clear();
     }
 
     pub fn create_ball(&mut self, pos: Vec2) {
+        let id = self.next_ball_id;
+        self.next_ball_id += 1;
+
         self.balls.push(Ball {
+            id,
             pos,
-            vel: 135. * random_direction(),
-            radius: 0.5,
+            vel: self.rules.ball_speed * random_direction(&mut self.rng),
+            radius: self.rules.ball_radius,
+            player: 0,
+            stuck: None,
         });
     }
+
+    /// Give the player a power-up, as if the paddle had just caught it
+    pub fn apply_power_up(&mut self, kind: PowerUpKind) {
+        let rules = &self.rules.power_ups;
+
+        match kind {
+            PowerUpKind::MultiBall => {
+                // Each ball splits into three, fanned out around where it was going, until there are too many
+                let spread = rules.multi_ball_spread;
+                for i in 0..self.balls.len() {
+                    if self.balls.len() + 2 > rules.multi_ball_limit {
+                        break;
+                    }
+
+                    let ball = Ball {
+                        stuck: None,
+                        ..self.balls[i]
+                    };
+
+                    for angle in [-spread, spread] {
+                        let id = self.next_ball_id;
+                        self.next_ball_id += 1;
+
+                        self.balls.push(Ball {
+                            id,
+                            vel: rotated(ball.vel, angle),
+                            ..ball
+                        });
+                    }
+                }
+            }
+            _ => self.effects.start(kind, rules.duration),
+        }
+    }
+
+    /// Add a ball resting on the middle of one of the `paddles`, which stays there until it's launched
+    pub fn serve_ball(&mut self, paddle: usize) {
+        let quad = &self.paddles[paddle];
+        let facing = self.paddle_facing(paddle);
+        let front = if facing > 0. {
+            quad.pos.y + quad.dims.y + self.rules.ball_radius
+        } else {
+            quad.pos.y - self.rules.ball_radius
+        };
+        let pos = Vec2::new(quad.pos.x + 0.5 * quad.dims.x, front);
+
+        self.create_ball(pos);
+        let ball = self.balls.last_mut().unwrap();
+        ball.player = paddle;
+        ball.stuck = Some(StuckToPaddle {
+            paddle,
+            offset: 0.,
+            release_in: f32::INFINITY,
+        });
+    }
+
+    /// Where new balls for one of the `paddles` appear: just in front of its middle
+    pub fn ball_spawn_pos(&self, paddle: usize) -> Vec2 {
+        let quad = &self.paddles[paddle];
+        let ahead = if self.paddle_facing(paddle) > 0. {
+            3. * quad.dims.y
+        } else {
+            -3. * quad.dims.y - 1.
+        };
+        quad.pos + Vec2::new(0.5 * quad.dims.x - 0.5, ahead)
+    }
+
+    /// 1 for paddles that send balls up, and -1 for the ones that send them down
+    pub fn paddle_facing(&self, paddle: usize) -> f32 {
+        facing(&self.paddles[paddle], self.bounds)
+    }
+
+    /// Left and right edges of the stretch that one of the `paddles` can slide along
+    pub fn paddle_lane(&self, paddle: usize) -> (f32, f32) {
+        paddle_lane(&self.paddles, paddle, self.bounds.x)
+    }
+
+    /// Advance the simulation by `dt` seconds and return the new world
+    pub fn step(&self, input: &FrameInput, dt: f32) -> World {
+        let mut next = World::default();
+        self.step_into(input, dt, &mut next);
+
+        next
+    }
+
+    /// Advance the simulation by `dt` seconds, with `input` for the first player and nothing from anyone else
+    pub fn step_into(&self, input: &FrameInput, dt: f32, next: &mut World) {
+        self.step_players_into(std::slice::from_ref(input), dt, next);
+    }
+
+    /// Advance the simulation by `dt` seconds, writing the new world into `next`.
+    ///
+    /// `inputs` has one input for each of the `paddles`, in the same order. Players without one don't do
+    /// anything. Anything already in `next` is thrown away, but its allocations are reused.
+    pub fn step_players_into(&self, inputs: &[FrameInput], dt: f32, next: &mut World) {
+        next.reset();
+        next.bounds = self.bounds;
+        next.brick_dims = self.brick_dims;
+        next.rules = self.rules;
+        next.next_ball_id = self.next_ball_id;
+        next.rng = self.rng;
+        next.effects = self.effects.ticked(dt);
+
+        let view_x = self.bounds.x;
+        let view_y = self.bounds.y;
+
+        let input = |player: usize| inputs.get(player).copied().unwrap_or_default();
+
+        // Balls are bounced in place, so they're copied over first and updated in `next`
+        if !inputs.iter().any(|input| input.clear_balls) {
+            next.balls.extend_from_slice(&self.balls);
+        }
+
+        for player in 0..self.paddles.len() {
+            if input(player).spawn_ball {
+                next.create_ball(self.ball_spawn_pos(player));
+                let ball = next.balls.last_mut().unwrap();
+                ball.player = player;
+                ball.vel.y *= self.paddle_facing(player);
+            }
+        }
+
+        // Update the paddles
+        for (player, paddle) in self.paddles.iter().enumerate() {
+            let input = input(player);
+            let mut paddle_x_vel = 0.;
+            if input.left {
+                paddle_x_vel -= self.rules.paddle_speed;
+            }
+            if input.right {
+                paddle_x_vel += self.rules.paddle_speed;
+            }
+
+            let mut next_paddle = *paddle;
+
+            // Grow or shrink around the middle when the wide paddle power-up starts or runs out
+            let mut width = self.rules.paddle_width;
+            if next.effects.wide_paddle > 0. {
+                width *= self.rules.power_ups.wide_paddle_scale;
+            }
+            let x = paddle.pos.x + 0.5 * (paddle.dims.x - width);
+            next_paddle.dims.x = width;
+
+            // Update movement
+            let (left, right) = paddle_lane(&self.paddles, player, view_x);
+            next_paddle.pos.x = (x + dt * paddle_x_vel)
+                // Keep the paddle in its lane
+                .clamp(left, (right - width).max(left));
+            // Paddles only slide left & right, so don't modify pos.y
+            next_paddle.pos.y = paddle.pos.y;
+
+            // Track how fast it actually moved, after bumping into the walls
+            next_paddle.vel = Vec2::zero();
+            if dt > 0. {
+                next_paddle.vel.x = (next_paddle.pos.x - x) / dt;
+            }
+
+            next.paddles.push(next_paddle);
+        }
+
+        // Unbreakable bricks always get copied over
+        next.unbreakable_bricks
+            .extend_from_slice(&self.unbreakable_bricks);
+
+        // Bricks don't move, so the grid carries over until one of them breaks
+        if self.brick_grid.len() == self.bricks.len() + self.unbreakable_bricks.len() {
+            next.brick_grid.clone_from(&self.brick_grid);
+        } else {
+            // Someone changed the bricks without rebuilding it
+            build_brick_grid(
+                &mut next.brick_grid,
+                self.bounds,
+                self.brick_dims,
+                &self.bricks,
+                &self.unbreakable_bricks,
+            );
+        }
+
+        // Move every ball, bouncing off the first thing it reaches until it runs out of time this step.
+        // Bricks break as soon as they run out of hit points, so balls after that pass right through them.
+        let mut hp: Vec<u32> = self.bricks.iter().map(|brick| brick.hp).collect();
+        // Whoever hit each brick last gets the credit for it
+        let mut hit_by = vec![0; self.bricks.len()];
+        let mut nearby = vec![];
+        let mut damaged = vec![];
+        let events = &mut next.events;
+        let paddles = &next.paddles;
+        let brick_grid = &next.brick_grid;
+        let effects = next.effects;
+        let base_speed = self.rules.ball_speed;
+        let speed_rules = &self.rules.speed;
+        next.balls.retain_mut(|ball| {
+            // Stuck balls ride along with their paddle until they let go
+            if let Some(stuck) = &mut ball.stuck {
+                let paddle = &paddles[stuck.paddle];
+                let facing = facing(paddle, self.bounds);
+                ball.pos.x = paddle.pos.x + 0.5 * paddle.dims.x + stuck.offset;
+                stuck.release_in -= dt;
+                if stuck.release_in <= 0. || input(stuck.paddle).launch {
+                    ball.stuck = None;
+                    ball.vel = paddle_bounce(ball, paddle, facing, &self.rules);
+                }
+
+                return true;
+            }
+
+            let start = ball.pos;
+            damaged.clear();
+
+            // Balls get faster the longer they're in play
+            speed_up(ball, base_speed * speed_rules.per_second * dt);
+
+            // Slowing balls down is the same as running their part of the step with less time
+            let mut time_left = dt;
+            if effects.slow_ball > 0. {
+                time_left *= self.rules.power_ups.slow_ball_scale;
+            }
+            for _ in 0..MAX_BOUNCES_PER_STEP {
+                let motion = time_left * ball.vel;
+
+                // Only check bricks close to where this ball will be this step
+                let swept =
+                    Aabb::new_from_quad(ball.pos, motion).padded(Vec2::broadcast(ball.radius));
+                brick_grid.query(&swept, &mut nearby);
+
+                let Some((hit, target)) = first_hit(
+                    ball,
+                    motion,
+                    &nearby,
+                    &self.bricks,
+                    &hp,
+                    &self.unbreakable_bricks,
+                    paddles,
+                ) else {
+                    // Nothing in the way
+                    ball.pos += motion;
+                    break;
+                };
+
+                ball.pos = hit.pos;
+
+                // Landing on the front of a paddle lets its player aim
+                let on_paddle = match target {
+                    HitTarget::Paddle(i) => {
+                        let facing = facing(&paddles[i], self.bounds);
+                        (hit.normal.y * facing > 0.).then_some((i, facing))
+                    }
+                    _ => None,
+                };
+                ball.vel = match on_paddle {
+                    Some((i, facing)) => paddle_bounce(ball, &paddles[i], facing, &self.rules),
+                    None => ball.vel.reflected(hit.normal),
+                };
+                time_left *= 1. - hit.t;
+
+                if let Some((i, _)) = on_paddle {
+                    ball.player = i;
+                    speed_up(ball, base_speed * speed_rules.per_paddle_hit);
+                    events.push(Event::PaddleHit { ball: *ball });
+
+                    if effects.sticky_paddle > 0. {
+                        let paddle = &paddles[i];
+                        ball.stuck = Some(StuckToPaddle {
+                            paddle: i,
+                            offset: ball.pos.x - (paddle.pos.x + 0.5 * paddle.dims.x),
+                            release_in: self.rules.power_ups.sticky_hold,
+                        });
+                        break;
+                    }
+                }
+
+                // A ball wedged between two bricks can bounce back and forth without going anywhere, so each
+                // ball only gets to damage a brick once per step
+                if let HitTarget::Brick(i) = target {
+                    if !damaged.contains(&i) {
+                        damaged.push(i);
+                        hp[i] -= 1;
+                        hit_by[i] = ball.player;
+                    }
+
+                    let brick = &self.bricks[i].quad;
+                    if brick.pos.y + brick.dims.y >= view_y - speed_rules.back_row_depth {
+                        speed_up(ball, base_speed * speed_rules.per_back_row_hit);
+                    }
+                }
+            }
+
+            ball.vel = limit_speed(ball.vel, base_speed, speed_rules);
+
+            // If it's still in bounds, keep it for the next frame
+            // (TODO: include radius in this math)
+            let in_bounds =
+                (0. < start.x && start.x < view_x) && (0. < start.y && start.y < view_y);
+            if !in_bounds {
+                events.push(Event::BallLost { ball: *ball });
+            }
+
+            in_bounds
+        });
+
+        if self.rules.ball_collisions {
+            collide_balls(&mut next.balls);
+        }
+
+        // Lasers fly straight ahead, and the first brick they reach takes a hit
+        for laser in &self.lasers {
+            let quad = &laser.quad;
+            let motion = dt * quad.vel;
+            let swept = Aabb::new_from_quad(
+                quad.pos + Vec2::zero().min_by_component(motion),
+                quad.dims + motion.abs(),
+            );
+            next.brick_grid.query(&swept, &mut nearby);
+
+            match first_laser_hit(
+                quad,
+                motion,
+                &nearby,
+                &self.bricks,
+                &hp,
+                &self.unbreakable_bricks,
+            ) {
+                Some(HitTarget::Brick(i)) => {
+                    hp[i] -= 1;
+                    hit_by[i] = laser.player;
+                }
+                Some(_) => {}
+                None => {
+                    let mut laser = *laser;
+                    laser.quad.pos += motion;
+                    if laser.quad.pos.y < view_y && laser.quad.pos.y + laser.quad.dims.y > 0. {
+                        next.lasers.push(laser);
+                    }
+                }
+            }
+        }
+
+        // Fire from both ends of every paddle
+        if next.effects.laser > 0. && next.effects.laser_cooldown == 0. {
+            next.effects.laser_cooldown = self.rules.power_ups.laser_interval;
+
+            for (player, paddle) in next.paddles.iter().enumerate() {
+                let facing = facing(paddle, self.bounds);
+                let y = if facing > 0. {
+                    paddle.dims.y
+                } else {
+                    -LASER_DIMS.y
+                };
+                for x in [0., paddle.dims.x - LASER_DIMS.x] {
+                    let quad = Quad {
+                        pos: paddle.pos + Vec2::new(x, y),
+                        vel: Vec2::new(0., facing * self.rules.power_ups.laser_speed),
+                        dims: LASER_DIMS,
+                        color: color::RED,
+                    };
+                    next.lasers.push(Laser { quad, player });
+                }
+            }
+        }
+
+        // Capsules fall until a paddle catches them or they leave the playfield
+        let mut collected = vec![];
+        for capsule in &self.capsules {
+            let mut capsule = *capsule;
+            capsule.quad.pos += dt * capsule.quad.vel;
+
+            let capsule_box = Aabb::new_from_quad(capsule.quad.pos, capsule.quad.dims);
+            let caught = next.paddles.iter().any(|paddle| {
+                capsule_box.intersects_with_aabb(&Aabb::new_from_quad(paddle.pos, paddle.dims))
+            });
+            if caught {
+                collected.push(capsule.kind);
+            } else if capsule_box.max.y > 0. && capsule_box.min.y < view_y {
+                next.capsules.push(capsule);
+            }
+        }
+
+        for kind in collected {
+            next.apply_power_up(kind);
+            next.events.push(Event::PowerUpCollected { kind });
+        }
+
+        // Bricks that ran out of hit points are deleted by omission
+        let any_broken = hp.contains(&0);
+        for ((brick, hp), player) in self.bricks.iter().zip(hp).zip(hit_by) {
+            let damaged = hp < brick.hp;
+            let brick = Brick { hp, ..*brick };
+
+            if hp == 0 {
+                // Power-ups fall towards whoever broke the brick
+                if let Some(kind) = brick.drops.roll(&mut next.rng) {
+                    let center = brick.quad.pos + 0.5 * brick.quad.dims;
+                    let speed = self.rules.power_ups.capsule_speed;
+                    let mut capsule = Capsule::new(kind, center, speed);
+                    if let Some(paddle) = next.paddles.get(player) {
+                        capsule.quad.vel.y *= facing(paddle, self.bounds);
+                    }
+                    next.capsules.push(capsule);
+                }
+
+                next.events.push(Event::BrickBroken { brick, player });
+            } else {
+                if damaged {
+                    next.events.push(Event::BrickDamaged { brick, player });
+                }
+                next.bricks.push(brick);
+            }
+        }
+
+        if any_broken {
+            next.rebuild_brick_grid();
+        }
+    }
+
+    /// Blend the moving parts of `prev` and `self` for rendering between steps.
+    ///
+    /// `alpha` runs from 0 (all `prev`) to 1 (all `self`). Balls that don't exist in both worlds are
+    /// drawn where they are in `self`.
+    pub fn interpolated(&self, prev: &World, alpha: f32) -> World {
+        let mut world = self.clone();
+
+        for (paddle, prev) in world.paddles.iter_mut().zip(&prev.paddles) {
+            paddle.pos = lerp(prev.pos, paddle.pos, alpha);
+        }
+
+        // Balls are only ever appended with increasing ids or removed, so both lists are sorted by id
+        let mut prev_balls = prev.balls.iter().peekable();
+        for ball in &mut world.balls {
+            while prev_balls.next_if(|prev| prev.id < ball.id).is_some() {}
+
+            if let Some(prev) = prev_balls.next_if(|prev| prev.id == ball.id) {
+                ball.pos = lerp(prev.pos, ball.pos, alpha);
+            }
+        }
+
+        world
+    }
+}
+
+fn lerp(a: Vec2, b: Vec2, t: f32) -> Vec2 {
+    a + t * (b - a)
+}
+
+/// Cells in `World::brick_grid` fit one brick and the gap around it
+fn build_brick_grid(
+    grid: &mut UniformGrid,
+    bounds: Vec2,
+    brick_dims: Vec2,
+    bricks: &[Brick],
+    unbreakable_bricks: &[Quad],
+) {
+    let boxes = bricks
+        .iter()
+        .map(|brick| &brick.quad)
+        .chain(unbreakable_bricks)
+        .map(|quad| Aabb::new_from_quad(quad.pos, quad.dims));
+    let bounds = Aabb::new_from_quad(Vec2::zero(), bounds);
+
+    grid.rebuild(bounds, brick_dims + Vec2::one(), boxes);
+}
+
+/// Most surfaces a ball can bounce off of in one step. Anything past this and the ball stops for the rest of
+/// the step, which keeps balls wedged into corners from looping forever.
+const MAX_BOUNCES_PER_STEP: usize = 8;
+
+#[derive(Copy, Clone, Debug)]
+enum HitTarget {
+    Brick(usize),
+    UnbreakableBrick,
+    Paddle(usize),
+}
+
+/// Find the first quad that `ball` reaches while moving along `motion`.
+///
+/// Only the bricks in `nearby` are checked, using the same ids as `World::brick_grid`. Bricks with no `hp`
+/// left have already broken this step, and are skipped.
+fn first_hit(
+    ball: &Ball,
+    motion: Vec2,
+    nearby: &[u32],
+    bricks: &[Brick],
+    hp: &[u32],
+    unbreakable_bricks: &[Quad],
+    paddles: &[Quad],
+) -> Option<(HitInfo, HitTarget)> {
+    let sweep = |quad: &Quad| {
+        Aabb::new_from_quad(quad.pos, quad.dims).intersects_with_circle_sweep(
+            ball.pos,
+            ball.radius,
+            motion,
+        )
+    };
+
+    let mut first: Option<(HitInfo, HitTarget)> = None;
+    let mut consider = |hit: Option<HitInfo>, target: HitTarget| {
+        if let Some(hit) = hit {
+            if first.is_none_or(|(first, _)| hit.t < first.t) {
+                first = Some((hit, target));
+            }
+        }
+    };
+
+    for &id in nearby {
+        let id = id as usize;
+        if id < bricks.len() {
+            if hp[id] > 0 {
+                consider(sweep(&bricks[id].quad), HitTarget::Brick(id));
+            }
+        } else {
+            let brick = &unbreakable_bricks[id - bricks.len()];
+            consider(sweep(brick), HitTarget::UnbreakableBrick);
+        }
+    }
+
+    for (i, paddle) in paddles.iter().enumerate() {
+        consider(sweep(paddle), HitTarget::Paddle(i));
+    }
+
+    first
+}
+
+/// Shape of a laser shot
+const LASER_DIMS: Vec2 = Vec2::new(0.25, 1.5);
+
+/// Find the first brick that `laser` reaches while moving along `motion`, like [`first_hit()`] does for balls
+fn first_laser_hit(
+    laser: &Quad,
+    motion: Vec2,
+    nearby: &[u32],
+    bricks: &[Brick],
+    hp: &[u32],
+    unbreakable_bricks: &[Quad],
+) -> Option<HitTarget> {
+    let laser_box = Aabb::new_from_quad(laser.pos, laser.dims);
+    let sweep = |quad: &Quad| {
+        Aabb::new_from_quad(quad.pos, quad.dims)
+            .intersects_with_aabb_sweep(&laser_box, motion)
+            .filter(|hit| hit.t <= 1.)
+    };
+
+    let mut first: Option<(f32, HitTarget)> = None;
+    for &id in nearby {
+        let id = id as usize;
+        let (hit, target) = if id < bricks.len() {
+            if hp[id] == 0 {
+                continue;
+            }
+            (sweep(&bricks[id].quad), HitTarget::Brick(id))
+        } else {
+            (
+                sweep(&unbreakable_bricks[id - bricks.len()]),
+                HitTarget::UnbreakableBrick,
+            )
+        };
+
+        if let Some(hit) = hit {
+            if first.is_none_or(|(t, _)| hit.t < t) {
+                first = Some((hit.t, target));
+            }
+        }
+    }
+
+    first.map(|(_, target)| target)
+}
+
+/// Bounce overlapping balls off of each other, like billiard balls.
+///
+/// Heavier (bigger) balls get pushed around less, and nothing is lost in the collision.
+fn collide_balls(balls: &mut [Ball]) {
+    // Sort by the left edge of each ball, so we only need to check balls that overlap along x
+    let mut order: Vec<usize> = (0..balls.len()).collect();
+    order.sort_unstable_by(|&a, &b| {
+        let a = balls[a].pos.x - balls[a].radius;
+        let b = balls[b].pos.x - balls[b].radius;
+        a.total_cmp(&b)
+    });
+
+    for (i, &a) in order.iter().enumerate() {
+        for &b in &order[i + 1..] {
+            let right_edge = balls[a].pos.x + balls[a].radius;
+            let left_edge = balls[b].pos.x - balls[b].radius;
+            if left_edge > right_edge {
+                // Everything after this is further right, so nothing else can touch `a`
+                break;
+            }
+
+            let [a, b] = balls.get_disjoint_mut([a, b]).unwrap();
+            collide_ball_pair(a, b);
+        }
+    }
+}
+
+fn collide_ball_pair(a: &mut Ball, b: &mut Ball) {
+    let delta = b.pos - a.pos;
+    let dist = delta.mag();
+    let touching = a.radius + b.radius;
+
+    // Exactly on top of each other doesn't have a direction to push in, so let them drift apart
+    if dist >= touching || dist == 0. {
+        return;
+    }
+
+    let normal = delta / dist;
+
+    // Mass goes with area
+    let mass_a = a.radius * a.radius;
+    let mass_b = b.radius * b.radius;
+
+    // Move them apart so they're just touching, and heavier balls move less
+    let overlap = touching - dist;
+    a.pos -= (overlap * mass_b / (mass_a + mass_b)) * normal;
+    b.pos += (overlap * mass_a / (mass_a + mass_b)) * normal;
+
+    // Only bounce if they're moving towards each other, otherwise they're already separating
+    let closing_speed = (b.vel - a.vel).dot(normal);
+    if closing_speed >= 0. {
+        return;
+    }
+
+    // Perfectly elastic impulse along the normal
+    let impulse = -2. * closing_speed / (1. / mass_a + 1. / mass_b);
+    a.vel -= (impulse / mass_a) * normal;
+    b.vel += (impulse / mass_b) * normal;
+}
+
+/// Which way `paddle` sends balls: 1 for up from the bottom half of the playfield, and -1 for down from the top
+fn facing(paddle: &Quad, bounds: Vec2) -> f32 {
+    if paddle.pos.y + 0.5 * paddle.dims.y > 0.5 * bounds.y {
+        -1.
+    } else {
+        1.
+    }
+}
+
+/// The stretch of the playfield that one of `paddles` can slide along. Paddles at the same height split the
+/// width between them evenly, in order.
+fn paddle_lane(paddles: &[Quad], i: usize, view_x: f32) -> (f32, f32) {
+    let beside = |j: &usize| paddles[*j].pos.y == paddles[i].pos.y;
+    let count = (0..paddles.len()).filter(beside).count() as f32;
+    let before = (0..i).filter(beside).count() as f32;
+
+    let width = view_x / count;
+    (before * width, (before + 1.) * width)
+}
+
+/// Velocity for a ball bouncing off the front of `paddle`, aimed by where along the paddle it lands.
+/// `facing` is the paddle's [`facing()`].
+fn paddle_bounce(ball: &Ball, paddle: &Quad, facing: f32, rules: &Rules) -> Vec2 {
+    let speed = ball.vel.mag();
+    let cone = rules.paddle_bounce_cone;
+
+    // -1 on the left edge, 0 in the middle, and 1 on the right edge
+    let half_width = 0.5 * paddle.dims.x;
+    let offset = ((ball.pos.x - (paddle.pos.x + half_width)) / half_width).clamp(-1., 1.);
+
+    // θ is measured from straight ahead, and positive is to the right
+    let θ = offset * cone;
+    let mut vel = speed * Vec2::new(f32::sin(θ), f32::cos(θ));
+
+    // A moving paddle drags the ball along with it a bit, but it still can't leave the cone
+    vel.x += rules.paddle_vel_influence * paddle.vel.x;
+    let θ = f32::atan2(vel.x, vel.y).clamp(-cone, cone);
+
+    speed * Vec2::new(f32::sin(θ), facing * f32::cos(θ))
+}
+
+/// Make `ball` go `amount` faster in the same direction
+fn speed_up(ball: &mut Ball, amount: f32) {
+    let speed = ball.vel.mag();
+    if speed > 0. {
+        ball.vel *= (speed + amount) / speed;
+    }
+}
+
+/// Keep `vel` between the slowest and fastest speeds, and steep enough that it isn't stuck going sideways.
+/// Balls that aren't moving at all are left alone.
+fn limit_speed(vel: Vec2, base_speed: f32, rules: &SpeedRules) -> Vec2 {
+    let speed = vel.mag();
+    if speed == 0. {
+        return vel;
+    }
+    let speed = speed.clamp(rules.min * base_speed, rules.max * base_speed);
+    let mut vel = vel.normalized() * speed;
+
+    let min_y = rules.min_vertical * speed;
+    if vel.y.abs() < min_y {
+        vel.y = min_y.copysign(vel.y);
+        vel.x = (speed * speed - min_y * min_y).sqrt().copysign(vel.x);
+    }
+
+    vel
 }
 
-fn random_direction() -> Vec2 {
+/// Turn `v` counterclockwise by `angle` radians
+fn rotated(v: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+}
+
+fn random_direction(rng: &mut Pcg32) -> Vec2 {
     use rand::prelude::*;
 
     // Random angle from (π/2, 3π/4) - this is the center half of the hemisphere
     // facing up in the simulation
-    let t: f32 = rand::rng().random();
+    let t: f32 = rng.random();
     let θ: f32 = 0.5 * std::f32::consts::PI * t + 0.25 * std::f32::consts::PI;
 
     Vec2::new(f32::cos(θ), f32::sin(θ))
 }
+
+#[cfg(test)]
+mod t {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const DT: f32 = 5e-3;
+    const SEED: u64 = 0xb0a7;
+
+    /// Bricks in each row of the default level
+    const COLUMNS: usize = 14;
+
+    #[test]
+    fn check_paddle_moves_and_stays_in_bounds() {
+        let mut world = World::new(1.5, SEED);
+        world.balls.clear();
+
+        let start_x = world.paddles[0].pos.x;
+        let input = FrameInput {
+            left: true,
+            ..Default::default()
+        };
+
+        let next = world.step(&input, DT);
+        assert_eq!(
+            next.paddles[0].pos.x,
+            start_x - DT * world.rules.paddle_speed
+        );
+        assert_eq!(next.paddles[0].pos.y, world.paddles[0].pos.y);
+
+        // Hold left for a long time and we should be pinned against the wall
+        for _ in 0..1_000 {
+            world = world.step(&input, DT);
+        }
+        assert_eq!(world.paddles[0].pos.x, 0.);
+    }
+
+    #[test]
+    fn check_ball_breaks_brick() {
+        let mut world = World::new(1.5, SEED);
+        let brick_count = world.bricks.len();
+
+        // Put a ball just under the bottom-left brick, moving up into it
+        let brick = world.bricks[brick_count - COLUMNS].quad;
+        world.balls = vec![Ball {
+            pos: brick.pos + Vec2::new(0.5 * brick.dims.x, -0.25),
+            vel: Vec2::new(0., 100.),
+            radius: 0.5,
+            ..Default::default()
+        }];
+
+        let next = world.step(&FrameInput::default(), DT);
+        assert_eq!(next.bricks.len(), brick_count - 1);
+        assert_eq!(next.balls.len(), 1);
+        assert!(next.balls[0].vel.y < 0.);
+    }
+
+    #[test]
+    fn check_world_from_level() {
+        let level = Level::parse(
+            "paddle 10 1\nball_speed 50\nkey R brick red 2 5 0\nkey X wall gray\nboard\nR.R\n.X.\n",
+        )
+        .unwrap();
+        let world = World::from_level(&level, 1.5, SEED);
+
+        // Three columns of bricks and the gaps between them
+        assert_eq!(world.bounds.x, 3. * (BRICK_DIMS.x + 1.) + 1.);
+        assert_eq!(world.paddles[0].dims, Vec2::new(10., 1.));
+        assert_eq!(world.rules.ball_speed, 50.);
+
+        assert_eq!(world.bricks.len(), 2);
+        assert_eq!(world.bricks[1].quad.pos.x, 2. * (BRICK_DIMS.x + 1.) + 1.);
+        assert_eq!(world.bricks[1].hp, 2);
+
+        // The wall is in the middle of the second row, after the three around the playfield
+        let wall = world.unbreakable_bricks[3];
+        assert_eq!(wall.pos.x, BRICK_DIMS.x + 2.);
+        assert_eq!(wall.pos.y, world.bricks[0].quad.pos.y - (BRICK_DIMS.y + 1.));
+    }
+
+    #[test]
+    fn check_large_bricks() {
+        let board = vec!["R".repeat(14); 20].join("\n");
+        let level = Level::parse(&format!(
+            "brick 2000 1\nkey R brick red 1 1 0\nboard\n{board}\n"
+        ))
+        .unwrap();
+        let mut world = World::from_level(&level, 1.5, SEED);
+        let brick_count = world.bricks.len();
+
+        // Hits still find the right brick
+        let brick = world.bricks[brick_count - 1].quad;
+        world.balls = vec![Ball {
+            pos: brick.pos + Vec2::new(0.5 * brick.dims.x, -0.25),
+            vel: Vec2::new(0., 100.),
+            radius: 0.5,
+            ..Default::default()
+        }];
+        world = world.step(&FrameInput::default(), DT);
+        assert_eq!(world.bricks.len(), brick_count - 1);
+
+        // And the grid is small enough to carry from step to step without bogging down
+        for _ in 0..2_000 {
+            world = world.step(&FrameInput::default(), DT);
+        }
+        assert_eq!(
+            world.brick_grid.len(),
+            world.bricks.len() + world.unbreakable_bricks.len()
+        );
+    }
+
+    #[test]
+    fn check_tough_bricks_take_several_hits() {
+        let mut world = World::new(1.5, SEED);
+
+        // Top left is a red brick, which takes 3 hits. Keep it by itself so the ball doesn't hit anything else.
+        world.bricks.truncate(1);
+        let brick_count = world.bricks.len();
+        let brick = world.bricks[0];
+        assert_eq!(brick.hp, 3);
+        assert_eq!(brick.color(), color::RED);
+
+        let hit = |world: &World| {
+            let mut world = world.clone();
+            world.balls = vec![Ball {
+                pos: brick.quad.pos + Vec2::new(0.5 * brick.quad.dims.x, -0.25),
+                vel: Vec2::new(0., 100.),
+                radius: 0.5,
+                ..Default::default()
+            }];
+            world.step(&FrameInput::default(), DT)
+        };
+
+        let world = hit(&world);
+        assert_eq!(world.bricks.len(), brick_count);
+        assert_eq!(world.bricks[0].hp, 2);
+        assert!(matches!(world.events[..], [Event::BrickDamaged { .. }]));
+
+        // It gets grayer with each hit
+        let gray_dist = |brick: &Brick| (brick.color() - color::GRAY).mag();
+        let once = world.bricks[0];
+        assert!(gray_dist(&once) < gray_dist(&brick));
+
+        let world = hit(&world);
+        assert_eq!(world.bricks[0].hp, 1);
+        assert!(gray_dist(&world.bricks[0]) < gray_dist(&once));
+
+        let world = hit(&world);
+        assert_eq!(world.bricks.len(), brick_count - 1);
+        assert!(matches!(
+            world.events[..],
+            [Event::BrickBroken { brick: b, .. }] if b.quad.pos == brick.quad.pos
+        ));
+    }
+
+    #[test]
+    fn check_clear_and_spawn_balls() {
+        let world = World::new(1.5, SEED);
+        assert_eq!(world.balls.len(), 1);
+
+        let input = FrameInput {
+            spawn_ball: true,
+            ..Default::default()
+        };
+        let world = world.step(&input, DT);
+        assert_eq!(world.balls.len(), 2);
+
+        let input = FrameInput {
+            clear_balls: true,
+            ..Default::default()
+        };
+        let world = world.step(&input, DT);
+        assert_eq!(world.balls.len(), 0);
+    }
+
+    #[test]
+    fn check_ball_lost_below_paddle() {
+        let mut world = World::new(1.5, SEED);
+        world.balls[0].pos = Vec2::new(0.5 * world.bounds.x, 0.1);
+        world.balls[0].vel = Vec2::new(0., -100.);
+
+        let world = world.step(&FrameInput::default(), DT);
+        let world = world.step(&FrameInput::default(), DT);
+        assert_eq!(world.balls.len(), 0);
+        assert!(matches!(world.events[..], [Event::BallLost { .. }]));
+    }
+
+    #[test]
+    fn check_interpolated_matches_balls_by_id() {
+        let prev = World::new(1.5, SEED);
+        let input = FrameInput {
+            spawn_ball: true,
+            right: true,
+            ..Default::default()
+        };
+        let world = prev.step(&input, DT);
+        assert_eq!(world.balls.len(), 2);
+
+        let halfway = world.interpolated(&prev, 0.5);
+        assert_eq!(
+            halfway.paddles[0].pos,
+            prev.paddles[0].pos + 0.5 * (world.paddles[0].pos - prev.paddles[0].pos)
+        );
+        assert_eq!(
+            halfway.balls[0].pos,
+            prev.balls[0].pos + 0.5 * (world.balls[0].pos - prev.balls[0].pos)
+        );
+
+        // The new ball wasn't around last step, so it doesn't move
+        assert_eq!(halfway.balls[1].pos, world.balls[1].pos);
+    }
+
+    #[test]
+    fn check_fast_ball_does_not_tunnel() {
+        let mut world = World::new(1.5, SEED);
+        let brick_count = world.bricks.len();
+
+        // Start under a brick that's over the paddle, moving up 20 units in a single step
+        let brick = world.bricks[brick_count - COLUMNS / 2].quad;
+        world.balls[0].pos = brick.pos + Vec2::new(0.5 * brick.dims.x, -5.);
+        world.balls[0].vel = Vec2::new(0., 20. / DT);
+
+        let next = world.step(&FrameInput::default(), DT);
+        assert_eq!(next.bricks.len(), brick_count - 1);
+        assert!(matches!(
+            next.events[..],
+            [Event::PaddleHit { .. }, Event::BrickBroken { brick: b, .. }] if b.quad.pos == brick.pos
+        ));
+
+        // It bounced back down, then off of the paddle, and it's heading back up again
+        assert_eq!(next.balls.len(), 1);
+        assert!(next.balls[0].vel.y > 0.);
+    }
+
+    #[test]
+    fn check_paddle_aims_balls() {
+        let mut world = World::new(1.5, SEED);
+        let paddle = world.paddles[0];
+        let cone = world.rules.paddle_bounce_cone;
+        world.rules.speed = SpeedRules::FIXED;
+
+        // Drop balls straight down onto the middle and both edges of the paddle
+        let top = paddle.pos.y + paddle.dims.y + 0.75;
+        let drop_at = |x: f32| Ball {
+            pos: Vec2::new(paddle.pos.x + x, top),
+            vel: Vec2::new(0., -135.),
+            radius: 0.5,
+            ..Default::default()
+        };
+        world.balls = vec![
+            drop_at(0.5 * paddle.dims.x),
+            drop_at(0.),
+            drop_at(paddle.dims.x),
+        ];
+
+        let next = world.step(&FrameInput::default(), DT);
+        let angles: Vec<f32> = next
+            .balls
+            .iter()
+            .map(|ball| f32::atan2(ball.vel.x, ball.vel.y))
+            .collect();
+
+        assert!(angles[0].abs() < 1e-5);
+        assert!((angles[1] + cone).abs() < 1e-5);
+        assert!((angles[2] - cone).abs() < 1e-5);
+
+        // Speed doesn't change
+        for ball in &next.balls {
+            assert!((ball.vel.mag() - 135.).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn check_moving_paddle_drags_balls() {
+        let mut world = World::new(1.5, SEED);
+        let paddle = world.paddles[0];
+
+        let top = paddle.pos.y + paddle.dims.y + 0.75;
+        world.balls[0].pos = Vec2::new(paddle.pos.x + 0.5 * paddle.dims.x, top);
+        world.balls[0].vel = Vec2::new(0., -135.);
+
+        // Get the paddle moving first, then let the ball land on it
+        let input = FrameInput {
+            right: true,
+            ..Default::default()
+        };
+        let next = world.step(&input, 1e-4).step(&input, DT);
+        assert!(next.balls[0].vel.x > 0.);
+        assert!(next.balls[0].vel.y > 0.);
+    }
+
+    #[test]
+    fn check_balls_collide() {
+        let mut world = World::new(1.5, SEED);
+        world.bricks.clear();
+        world.rules.speed = SpeedRules::FIXED;
+
+        // Two balls heading straight at each other in the middle of the board
+        let center = 0.5 * world.bounds;
+        let ball = Ball {
+            radius: 0.5,
+            ..Default::default()
+        };
+        world.balls = vec![
+            Ball {
+                id: 0,
+                pos: center - Vec2::new(0.55, 0.),
+                vel: Vec2::new(100., 0.),
+                ..ball
+            },
+            Ball {
+                id: 1,
+                pos: center + Vec2::new(0.55, 0.),
+                vel: Vec2::new(-50., 0.),
+                ..ball
+            },
+        ];
+
+        // The same size means they just swap velocities
+        let next = world.step(&FrameInput::default(), DT);
+        assert_eq!(next.balls[0].vel, Vec2::new(-50., 0.));
+        assert_eq!(next.balls[1].vel, Vec2::new(100., 0.));
+        assert!((next.balls[1].pos - next.balls[0].pos).mag() >= 1. - 1e-5);
+
+        // And with collisions off, they go right through each other
+        world.rules.ball_collisions = false;
+        let next = world.step(&FrameInput::default(), DT);
+        assert_eq!(next.balls[0].vel, Vec2::new(100., 0.));
+        assert_eq!(next.balls[1].vel, Vec2::new(-50., 0.));
+    }
+
+    #[test]
+    fn check_heavy_balls_push_light_balls() {
+        let mut world = World::new(1.5, SEED);
+        world.bricks.clear();
+        world.rules.speed = SpeedRules::FIXED;
+
+        let center = 0.5 * world.bounds;
+        world.balls = vec![
+            Ball {
+                id: 0,
+                pos: center - Vec2::new(1.5, 0.),
+                vel: Vec2::new(100., 0.),
+                radius: 1.,
+                ..Default::default()
+            },
+            Ball {
+                id: 1,
+                pos: center,
+                vel: Vec2::zero(),
+                radius: 0.5,
+                ..Default::default()
+            },
+        ];
+
+        let next = world.step(&FrameInput::default(), DT);
+
+        // The big ball keeps going, and the small one shoots off faster than it was hit
+        assert!(next.balls[0].vel.x > 0.);
+        assert!(next.balls[1].vel.x > 100.);
+
+        // Momentum is conserved
+        let momentum = |balls: &[Ball]| -> Vec2 {
+            balls
+                .iter()
+                .map(|ball| ball.radius * ball.radius * ball.vel)
+                .fold(Vec2::zero(), |a, b| a + b)
+        };
+        assert!((momentum(&next.balls) - momentum(&world.balls)).mag() < 1e-3);
+    }
+
+    #[test]
+    fn check_balls_speed_up() {
+        let mut world = World::new(1.5, SEED);
+        world.bricks.clear();
+        world.rebuild_brick_grid();
+        let base = world.rules.ball_speed;
+        let rules = world.rules.speed;
+
+        // Straight up and down between the paddle and the ceiling, for a few seconds
+        let paddle = world.paddles[0];
+        world.balls[0].pos = paddle.pos + Vec2::new(0.5 * paddle.dims.x, 5.);
+        world.balls[0].vel = Vec2::new(0., base);
+        let mut paddle_hits = 0;
+        for _ in 0..(5. / DT) as usize {
+            world = world.step(&FrameInput::default(), DT);
+            paddle_hits += world.events.len();
+        }
+        assert!(paddle_hits > 0);
+
+        let expected =
+            base * (1. + 5. * rules.per_second + paddle_hits as f32 * rules.per_paddle_hit);
+        let speed = world.balls[0].vel.mag();
+        assert!((speed - expected).abs() < 0.1, "{speed} vs {expected}");
+
+        // But only up to a point
+        world.balls[0].vel = Vec2::new(0., 10. * base);
+        let next = world.step(&FrameInput::default(), DT);
+        assert!((next.balls[0].vel.mag() - rules.max * base).abs() < 1e-3);
+    }
+
+    #[test]
+    fn check_back_rows_speed_balls_up() {
+        let mut world = World::new(1.5, SEED);
+        let base = world.rules.ball_speed;
+        world.rules.speed.per_second = 0.;
+
+        // Just the top left and bottom right bricks, so nothing else gets in the way
+        let last = world.bricks.len() - 1;
+        world.bricks = vec![world.bricks[0], world.bricks[last]];
+        world.rebuild_brick_grid();
+
+        // Hit the bottom brick, then the top one
+        for i in [1, 0] {
+            let brick = world.bricks[i].quad;
+            world.balls[0].pos = brick.pos + Vec2::new(0.5 * brick.dims.x, -1.);
+            world.balls[0].vel = Vec2::new(0., base);
+
+            let next = world.step(&FrameInput::default(), DT);
+            let speed_up = next.balls[0].vel.mag() / base - 1.;
+            if i == 0 {
+                assert!((speed_up - world.rules.speed.per_back_row_hit).abs() < 1e-4);
+            } else {
+                assert!(speed_up.abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn check_balls_dont_get_stuck_going_sideways() {
+        let mut world = World::new(1.5, SEED);
+        let base = world.rules.ball_speed;
+
+        world.balls[0].pos = 0.5 * world.bounds;
+        world.balls[0].vel = Vec2::new(base, 0.);
+
+        let next = world.step(&FrameInput::default(), DT);
+        let vel = next.balls[0].vel;
+        assert!(vel.y.abs() >= world.rules.speed.min_vertical * vel.mag() - 1e-3);
+        assert!((vel.mag() - base).abs() < 0.1);
+
+        // And slow balls get back up to speed
+        world.balls[0].vel = Vec2::new(0., 1.);
+        let next = world.step(&FrameInput::default(), DT);
+        assert!((next.balls[0].vel.mag() - world.rules.speed.min * base).abs() < 1e-3);
+    }
+
+    #[test]
+    fn check_same_seed_same_world() {
+        // Play the same inputs into a few worlds, and throw in a bunch of new balls to use the rng
+        let play = |seed: u64| {
+            let mut world = World::new(1.5, seed);
+            for i in 0..2_000 {
+                let input = FrameInput {
+                    left: (i / 100) % 3 == 0,
+                    right: (i / 100) % 3 == 1,
+                    spawn_ball: i % 50 == 0,
+                    ..Default::default()
+                };
+                world = world.step(&input, DT);
+            }
+
+            world
+        };
+
+        let a = play(1);
+        let b = play(1);
+        let c = play(2);
+
+        assert!(a == b, "Same seed and inputs gave different worlds");
+        assert!(a != c, "Different seeds gave the same world");
+    }
+
+    #[test]
+    fn check_broken_bricks_drop_power_ups() {
+        let mut world = World::new(1.5, SEED);
+
+        // Make the bottom middle brick always drop a wide paddle, then break it
+        let i = world.bricks.len() - COLUMNS / 2;
+        let mut weights = [0; PowerUpKind::COUNT];
+        weights[PowerUpKind::WidePaddle as usize] = 1;
+        world.bricks[i].drops = DropTable {
+            chance: 1.,
+            weights,
+        };
+
+        let brick = world.bricks[i].quad;
+        world.balls = vec![Ball {
+            pos: brick.pos + Vec2::new(0.5 * brick.dims.x, -0.25),
+            vel: Vec2::new(0., 100.),
+            radius: 0.5,
+            ..Default::default()
+        }];
+
+        let mut world = world.step(&FrameInput::default(), DT);
+        assert_eq!(world.capsules.len(), 1);
+        assert_eq!(world.capsules[0].kind, PowerUpKind::WidePaddle);
+
+        // It falls straight down onto the paddle
+        world.balls.clear();
+        let collected = Event::PowerUpCollected {
+            kind: PowerUpKind::WidePaddle,
+        };
+        for _ in 0..1_000 {
+            world = world.step(&FrameInput::default(), DT);
+            if world.events.contains(&collected) {
+                break;
+            }
+        }
+        assert!(world.events.contains(&collected));
+        assert!(world.capsules.is_empty());
+
+        let width = world.rules.paddle_width;
+        let world = world.step(&FrameInput::default(), DT);
+        assert_eq!(
+            world.paddles[0].dims.x,
+            width * world.rules.power_ups.wide_paddle_scale
+        );
+
+        // And it wears off
+        let mut world = world;
+        let steps = (world.rules.power_ups.duration / DT) as usize;
+        for _ in 0..steps {
+            world = world.step(&FrameInput::default(), DT);
+        }
+        assert_eq!(world.paddles[0].dims.x, width);
+    }
+
+    #[test]
+    fn check_multi_ball_splits_balls() {
+        let mut world = World::new(1.5, SEED);
+        let ball = world.balls[0];
+
+        world.apply_power_up(PowerUpKind::MultiBall);
+        assert_eq!(world.balls.len(), 3);
+
+        let ids: Vec<u32> = world.balls.iter().map(|ball| ball.id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+
+        // Same place and speed, different directions
+        let spread = world.rules.power_ups.multi_ball_spread;
+        for (split, angle) in world.balls[1..].iter().zip([-spread, spread]) {
+            assert_eq!(split.pos, ball.pos);
+            assert!((split.vel.mag() - ball.vel.mag()).abs() < 1e-3);
+            assert!((split.vel.dot(ball.vel) / ball.vel.mag_sq() - angle.cos()).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn check_lasers_damage_bricks() {
+        let mut world = World::new(1.5, SEED);
+        let brick_count = world.bricks.len();
+        world.balls.clear();
+        world.effects.laser = 5.;
+
+        let world = world.step(&FrameInput::default(), DT);
+        assert_eq!(world.lasers.len(), 2);
+
+        // Both shots reach the bottom row and break a brick each
+        let mut world = world;
+        for _ in 0..20 {
+            world = world.step(&FrameInput::default(), DT);
+        }
+        assert_eq!(world.bricks.len(), brick_count - 2);
+    }
+
+    #[test]
+    fn check_sticky_paddle_holds_balls() {
+        let mut world = World::new(1.5, SEED);
+        world.effects.sticky_paddle = 5.;
+
+        let paddle = world.paddles[0];
+        let top = paddle.pos.y + paddle.dims.y + 0.75;
+        world.balls[0].pos = Vec2::new(paddle.pos.x + 0.25 * paddle.dims.x, top);
+        world.balls[0].vel = Vec2::new(0., -135.);
+
+        let mut world = world.step(&FrameInput::default(), DT);
+        let stuck = world.balls[0].stuck.expect("Ball should be stuck");
+        assert!(world.balls[0].vel.y > 0.);
+
+        // It rides along with the paddle
+        let input = FrameInput {
+            right: true,
+            ..Default::default()
+        };
+        let start_x = world.paddles[0].pos.x;
+        for _ in 0..10 {
+            world = world.step(&input, DT);
+        }
+        let paddle = world.paddles[0];
+        assert!(paddle.pos.x > start_x);
+        assert!(
+            (world.balls[0].pos.x - (paddle.pos.x + 0.5 * paddle.dims.x) - stuck.offset).abs()
+                < 1e-4
+        );
+
+        // Then lets go and heads back up
+        let steps = (world.rules.power_ups.sticky_hold / DT) as usize + 1;
+        for _ in 0..steps {
+            world = world.step(&FrameInput::default(), DT);
+        }
+        assert_eq!(world.balls[0].stuck, None);
+        assert!(world.balls[0].pos.y > top);
+    }
+
+    #[test]
+    fn check_held_balls_launch_by_offset() {
+        let mut world = World::new(1.5, SEED);
+        world.balls.clear();
+        world.serve_ball(0);
+        let speed = world.balls[0].vel.mag();
+        let launch = FrameInput {
+            launch: true,
+            ..Default::default()
+        };
+
+        // Served balls sit in the middle, and go straight up
+        let next = world.step(&launch, DT);
+        let ball = next.balls[0];
+        assert_eq!(ball.stuck, None);
+        assert!(ball.vel.x.abs() < 1e-4);
+        assert!((ball.vel.mag() - speed).abs() < 1e-3);
+
+        // Balls held towards the ends go off that way
+        for offset in [-8., 8.] {
+            let mut world = world.clone();
+            world.balls[0].stuck.as_mut().unwrap().offset = offset;
+
+            let ball = world.step(&launch, DT).balls[0];
+            assert!(ball.vel.x.signum() == f32::signum(offset), "{:?}", ball.vel);
+            assert!(ball.vel.y > 0.);
+        }
+
+        // Holding them doesn't let go on its own
+        for _ in 0..100 {
+            world = world.step(&FrameInput::default(), DT);
+        }
+        assert!(world.balls[0].stuck.is_some());
+    }
+
+    #[test]
+    fn check_co_op_paddles_keep_to_their_halves() {
+        let mut world = World::from_level_with(&Level::default_level(), 1.5, SEED, Mode::CoOp);
+        assert_eq!(world.paddles.len(), 2);
+        assert_eq!(world.paddles[0].pos.y, world.paddles[1].pos.y);
+        let middle = 0.5 * world.bounds.x;
+
+        // Each starts in the middle of its half
+        let [a, b] = [world.paddles[0], world.paddles[1]];
+        assert_eq!(a.pos.x + 0.5 * a.dims.x, 0.5 * middle);
+        assert_eq!(b.pos.x + 0.5 * b.dims.x, 1.5 * middle);
+
+        // Run into each other in the middle
+        let inputs = [
+            FrameInput {
+                right: true,
+                ..Default::default()
+            },
+            FrameInput {
+                left: true,
+                ..Default::default()
+            },
+        ];
+        for _ in 0..1_000 {
+            let mut next = World::default();
+            world.step_players_into(&inputs, DT, &mut next);
+            world = next;
+        }
+        let [a, b] = [world.paddles[0], world.paddles[1]];
+        assert_eq!(a.pos.x + a.dims.x, middle);
+        assert_eq!(b.pos.x, middle);
+
+        // Each player only launches balls on their own paddle
+        world.balls.clear();
+        world.serve_ball(1);
+        let launch = |player: usize| {
+            let mut inputs = [FrameInput::default(); 2];
+            inputs[player].launch = true;
+            inputs
+        };
+        let mut next = World::default();
+        world.step_players_into(&launch(0), DT, &mut next);
+        assert!(next.balls[0].stuck.is_some());
+        world.step_players_into(&launch(1), DT, &mut next);
+        assert_eq!(next.balls[0].stuck, None);
+        assert_eq!(next.balls[0].player, 1);
+    }
+
+    #[test]
+    fn check_versus_paddle_at_the_top() {
+        let mut world = World::from_level_with(&Level::default_level(), 1.5, SEED, Mode::Versus);
+        world.balls.clear();
+        world.rules.speed = SpeedRules::FIXED;
+        assert_eq!(world.paddle_facing(0), 1.);
+        assert_eq!(world.paddle_facing(1), -1.);
+
+        // The board sits in the middle
+        let bottom = world
+            .bricks
+            .iter()
+            .map(|b| b.quad.pos.y)
+            .fold(f32::MAX, f32::min);
+        let top = world.bricks.iter().map(|b| b.quad.pos.y + b.quad.dims.y);
+        let top = top.fold(f32::MIN, f32::max);
+        assert!(
+            (bottom - (world.bounds.y - top)).abs() < 1e-3,
+            "{bottom} {top}"
+        );
+
+        // Balls bounce down off of the top paddle, and it's theirs now
+        let paddle = world.paddles[1];
+        world.balls = vec![Ball {
+            pos: paddle.pos + Vec2::new(0.5 * paddle.dims.x, -0.75),
+            vel: Vec2::new(0., 100.),
+            radius: 0.5,
+            ..Default::default()
+        }];
+        let next = world.step(&FrameInput::default(), DT);
+        assert!(next.balls[0].vel.y < 0.);
+        assert_eq!(next.balls[0].player, 1);
+        assert!(matches!(next.events[..], [Event::PaddleHit { ball }] if ball.player == 1));
+
+        // There's no wall behind it
+        world.paddles[1].pos.x = 0.;
+        world.balls[0].pos.y = world.bounds.y - 0.25;
+        let next = world
+            .step(&FrameInput::default(), DT)
+            .step(&FrameInput::default(), DT);
+        assert!(next.balls.is_empty());
+    }
+}